@@ -43,6 +43,38 @@ impl AABB {
 
         true
     }
+
+    /// Like `hit`, but returns the parameter range the ray spends inside
+    /// the box (clipped to [`tmin`, `tmax`]).
+    pub fn hit_range(&self, r: &Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
+        let mut t_min = tmin;
+        let mut t_max = tmax;
+
+        for i in 0..3 {
+            let invd = 1.0 / r.dir[i];
+            let mut t0 = (self.min_p[i] - r.orig[i]) * invd;
+            let mut t1 = (self.max_p[i] - r.orig[i]) * invd;
+            if invd < 0.0 {
+                std::mem::swap(&mut t1, &mut t0);
+            }
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max_p - self.min_p;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (self.min_p + self.max_p) * 0.5
+    }
 }
 
 pub fn surrounding_box(box0: AABB, box1: AABB) -> AABB {
@@ -21,10 +21,9 @@ impl XYrect {
             k,
         }
     }
-}
 
-impl Object for XYrect {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    /// Parameter and in-plane coordinates where the ray crosses the rect.
+    fn crossing(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
         let tin = (self.k - ray.orig.z) / ray.dir.z;
         if tin < t_min || tin > t_max {
             return None;
@@ -35,7 +34,13 @@ impl Object for XYrect {
         if x < self.x0 || x > self.x1 || y < self.y0 || y > self.y1 {
             return None;
         }
+        Some((tin, x, y))
+    }
+}
 
+impl Object for XYrect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (tin, x, y) = self.crossing(ray, t_min, t_max)?;
         let outward_normal = Vec3::new(0.0, 0.0, 1.0);
         let u = (x - self.x0) / (self.x1 - self.x0);
         let v = (y - self.y0) / (self.y1 - self.y0);
@@ -57,4 +62,258 @@ impl Object for XYrect {
     fn get_background(&self, _t: f64) -> Color {
         Color::zero()
     }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.crossing(ray, t_min, t_max).is_some()
+    }
+}
+
+pub struct XZrect {
+    mp: Arc<dyn Material>,
+    x0: f64,
+    x1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+}
+
+impl XZrect {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            mp,
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+        }
+    }
+
+    /// Parameter and in-plane coordinates where the ray crosses the rect.
+    fn crossing(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        let tin = (self.k - ray.orig.y) / ray.dir.y;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+
+        let x = ray.orig.x + tin * ray.dir.x;
+        let z = ray.orig.z + tin * ray.dir.z;
+        if x < self.x0 || x > self.x1 || z < self.z0 || z > self.z1 {
+            return None;
+        }
+        Some((tin, x, z))
+    }
+}
+
+impl Object for XZrect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (tin, x, z) = self.crossing(ray, t_min, t_max)?;
+        let outward_normal = Vec3::new(0.0, 1.0, 0.0);
+        let u = (x - self.x0) / (self.x1 - self.x0);
+        let v = (z - self.z0) / (self.z1 - self.z0);
+        let mut cur_rec =
+            HitRecord::new(ray.at(tin), Vec3::new(0.0, 1.0, 0.0), tin, self.mp.clone());
+        cur_rec.set_face_normal(ray, &outward_normal);
+        cur_rec.set_uv((u, v));
+        Some(cur_rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        let output_box = AABB::new(
+            Point3::new(self.x0, self.k - 0.0001, self.z0),
+            Point3::new(self.x1, self.k + 0.0001, self.z1),
+        );
+        Some(output_box)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.crossing(ray, t_min, t_max).is_some()
+    }
+}
+
+pub struct YZrect {
+    mp: Arc<dyn Material>,
+    y0: f64,
+    y1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+}
+
+impl YZrect {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            mp,
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+        }
+    }
+
+    /// Parameter and in-plane coordinates where the ray crosses the rect.
+    fn crossing(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        let tin = (self.k - ray.orig.x) / ray.dir.x;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+
+        let y = ray.orig.y + tin * ray.dir.y;
+        let z = ray.orig.z + tin * ray.dir.z;
+        if y < self.y0 || y > self.y1 || z < self.z0 || z > self.z1 {
+            return None;
+        }
+        Some((tin, y, z))
+    }
+}
+
+impl Object for YZrect {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (tin, y, z) = self.crossing(ray, t_min, t_max)?;
+        let outward_normal = Vec3::new(1.0, 0.0, 0.0);
+        let u = (y - self.y0) / (self.y1 - self.y0);
+        let v = (z - self.z0) / (self.z1 - self.z0);
+        let mut cur_rec =
+            HitRecord::new(ray.at(tin), Vec3::new(1.0, 0.0, 0.0), tin, self.mp.clone());
+        cur_rec.set_face_normal(ray, &outward_normal);
+        cur_rec.set_uv((u, v));
+        Some(cur_rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        let output_box = AABB::new(
+            Point3::new(self.k - 0.0001, self.y0, self.z0),
+            Point3::new(self.k + 0.0001, self.y1, self.z1),
+        );
+        Some(output_box)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.crossing(ray, t_min, t_max).is_some()
+    }
+}
+
+/// Axis-aligned box made of six rects. Named `Cuboid` rather than `Box`
+/// so that glob imports of this module don't shadow `std::boxed::Box`.
+pub struct Cuboid {
+    box_min: Point3,
+    box_max: Point3,
+    sides: HittableList,
+}
+
+impl Cuboid {
+    pub fn new(p0: Point3, p1: Point3, mp: Arc<dyn Material>) -> Self {
+        let mut sides = HittableList::new(true);
+
+        sides.push(Arc::new(XYrect::new(
+            p0.x,
+            p1.x,
+            p0.y,
+            p1.y,
+            p1.z,
+            mp.clone(),
+        )));
+        sides.push(Arc::new(FlipFace::new(Arc::new(XYrect::new(
+            p0.x,
+            p1.x,
+            p0.y,
+            p1.y,
+            p0.z,
+            mp.clone(),
+        )))));
+
+        sides.push(Arc::new(XZrect::new(
+            p0.x,
+            p1.x,
+            p0.z,
+            p1.z,
+            p1.y,
+            mp.clone(),
+        )));
+        sides.push(Arc::new(FlipFace::new(Arc::new(XZrect::new(
+            p0.x,
+            p1.x,
+            p0.z,
+            p1.z,
+            p0.y,
+            mp.clone(),
+        )))));
+
+        sides.push(Arc::new(YZrect::new(
+            p0.y,
+            p1.y,
+            p0.z,
+            p1.z,
+            p1.x,
+            mp.clone(),
+        )));
+        sides.push(Arc::new(FlipFace::new(Arc::new(YZrect::new(
+            p0.y, p1.y, p0.z, p1.z, p0.x, mp,
+        )))));
+
+        Self {
+            box_min: p0,
+            box_max: p1,
+            sides,
+        }
+    }
+}
+
+impl Object for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.sides.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(AABB::new(self.box_min, self.box_max))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.sides.occluded(ray, t_min, t_max)
+    }
+}
+
+/// Reverses the outward normal of the wrapped object, so that the faces on
+/// the negative side of a `Cuboid` point away from its interior.
+pub struct FlipFace {
+    ptr: Arc<dyn Object>,
+}
+
+impl FlipFace {
+    pub fn new(ptr: Arc<dyn Object>) -> Self {
+        Self { ptr }
+    }
+}
+
+impl Object for FlipFace {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.ptr.hit(ray, t_min, t_max)?;
+        rec.front_face = !rec.front_face;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.ptr.bounding_box(t0, t1)
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.ptr.get_background(t)
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.ptr.occluded(ray, t_min, t_max)
+    }
 }
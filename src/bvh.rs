@@ -3,6 +3,37 @@ pub use crate::object::*;
 pub use rand::Rng;
 pub use std::{cmp::Ordering, sync::Arc};
 
+/// How `BvhNode` partitions the objects under a node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SplitMethod {
+    /// Sort along a random axis and split at the median. Cheap to build,
+    /// but the tree ignores how the objects are laid out.
+    Median,
+    /// Binned surface area heuristic: tries a few planes per axis and keeps
+    /// the one with the lowest expected traversal cost.
+    Sah,
+}
+
+/// Objects to build a tree over, each with its bounding box.
+pub(crate) type BoxedObjects = Vec<(AABB, Arc<dyn Object>)>;
+
+const SAH_BINS: usize = 12;
+/// Cost of a box test relative to one object intersection, for the SAH.
+pub(crate) const TRAVERSAL_COST: f64 = 1.0;
+
+/// Shape of a built tree. `cost` is the SAH estimate of the work per ray,
+/// in units of one object intersection, for a ray known to hit the root box.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub max_depth: usize,
+    pub cost: f64,
+}
+
+/// Binary tree of objects, each node holding its children behind `Arc`s.
+/// Once built it is immutable: for animated scenes, where the boxes change
+/// every frame, use `LinearBvh` instead, which can `refit` them in place.
 pub struct BvhNode {
     left: Arc<dyn Object>,
     right: Arc<dyn Object>,
@@ -15,38 +46,96 @@ impl BvhNode {
         BvhNode::init(list.objects, time0, time1, list.if_dark)
     }
 
+    /// Builds a BVH over `objects`. Objects without a bounding box (such as
+    /// an infinite `Plane`) cannot go into the tree, so they are kept next to
+    /// it in a `HittableList` instead.
     pub fn init(
-        mut objects: Vec<Arc<dyn Object>>,
+        objects: Vec<Arc<dyn Object>>,
         time0: f64,
         time1: f64,
         if_dark: bool,
     ) -> Arc<dyn Object> {
-        let axis = rand::thread_rng().gen_range(0, 3);
+        Self::init_with(objects, time0, time1, if_dark, SplitMethod::Median).0
+    }
+
+    /// Like `init`, with a choice of split method, and reports the shape
+    /// of the resulting tree.
+    pub fn init_with(
+        objects: Vec<Arc<dyn Object>>,
+        time0: f64,
+        time1: f64,
+        if_dark: bool,
+        method: SplitMethod,
+    ) -> (Arc<dyn Object>, BvhStats) {
+        let mut bounded = vec![];
+        let mut unbounded = vec![];
+        for ob in objects {
+            match ob.bounding_box(time0, time1) {
+                Some(bbox) => bounded.push((bbox, ob)),
+                None => unbounded.push(ob),
+            }
+        }
+
+        let mut stats = BvhStats::default();
+        let tree = if bounded.is_empty() {
+            None
+        } else {
+            let (tree, bbox) = Self::build(bounded, method, if_dark, 0, &mut stats);
+            let root_area = bbox.surface_area();
+            stats.cost = if root_area > 0.0 {
+                stats.cost / root_area
+            } else {
+                stats.leaf_count as f64
+            };
+            Some(tree)
+        };
+        if unbounded.is_empty() {
+            return (tree.expect("cannot build a BVH without objects"), stats);
+        }
+
+        let mut list = HittableList::new(if_dark);
+        if let Some(tree) = tree {
+            list.push(tree);
+        }
+        for ob in unbounded {
+            list.push(ob);
+        }
+        (Arc::new(list), stats)
+    }
 
+    /// Builds the subtree over `objects`, adding to `stats` with the cost
+    /// weighted by (not yet divided by) the root's surface area.
+    fn build(
+        mut objects: BoxedObjects,
+        method: SplitMethod,
+        if_dark: bool,
+        depth: usize,
+        stats: &mut BvhStats,
+    ) -> (Arc<dyn Object>, AABB) {
+        stats.max_depth = stats.max_depth.max(depth);
         match objects.len() {
             0 => panic!(),
-            1 => objects.remove(0),
+            1 => {
+                let (bbox, ob) = objects.remove(0);
+                stats.leaf_count += 1;
+                stats.cost += bbox.surface_area();
+                (ob, bbox)
+            }
             _ => {
-                objects.sort_by(|a, b| {
-                    a.bounding_box(time0, time1).unwrap().min_p[axis]
-                        .partial_cmp(&b.bounding_box(time0, time1).unwrap().min_p[axis])
-                        .unwrap()
-                });
-
-                let mut left_objects = objects;
-                let right_objects = left_objects.split_off(left_objects.len() / 2);
-                let left = Self::init(left_objects, time0, time1, if_dark);
-                let right = Self::init(right_objects, time0, time1, if_dark);
-                let cur_box = surrounding_box(
-                    left.bounding_box(time0, time1).unwrap(),
-                    right.bounding_box(time0, time1).unwrap(),
-                );
-                Arc::new(Self {
+                let (right_objects, _) = split_objects(&mut objects, method);
+                let (left, left_box) = Self::build(objects, method, if_dark, depth + 1, stats);
+                let (right, right_box) =
+                    Self::build(right_objects, method, if_dark, depth + 1, stats);
+                let cur_box = surrounding_box(left_box, right_box);
+                stats.node_count += 1;
+                stats.cost += TRAVERSAL_COST * cur_box.surface_area();
+                let node = Self {
                     left,
                     right,
                     cur_box,
                     if_dark,
-                })
+                };
+                (Arc::new(node), cur_box)
             }
         }
     }
@@ -75,6 +164,11 @@ impl Object for BvhNode {
         }
     }
 
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.cur_box.hit(ray, t_min, t_max)
+            && (self.left.occluded(ray, t_min, t_max) || self.right.occluded(ray, t_min, t_max))
+    }
+
     fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
         Some(self.cur_box)
     }
@@ -87,3 +181,182 @@ impl Object for BvhNode {
         }
     }
 }
+
+/// Leaves the objects of the first child in `objects` and returns those of
+/// the second, with the axis they were split along. Only the boxes are
+/// looked at, so the objects can come with whatever the caller needs.
+pub(crate) fn split_objects<T>(
+    objects: &mut Vec<(AABB, T)>,
+    method: SplitMethod,
+) -> (Vec<(AABB, T)>, usize) {
+    match method {
+        SplitMethod::Median => split_median(objects),
+        SplitMethod::Sah => split_sah(objects),
+    }
+}
+
+/// Leaves the first half of `objects` in place and returns the second.
+fn split_median<T>(objects: &mut Vec<(AABB, T)>) -> (Vec<(AABB, T)>, usize) {
+    let axis = rand::thread_rng().gen_range(0, 3);
+    objects.sort_by(|a, b| a.0.min_p[axis].partial_cmp(&b.0.min_p[axis]).unwrap());
+    (objects.split_off(objects.len() / 2), axis)
+}
+
+/// Bins the box centroids along each axis and splits at the bin
+/// boundary with the lowest SAH cost. Falls back to the median when the
+/// centroids cannot be told apart.
+fn split_sah<T>(objects: &mut Vec<(AABB, T)>) -> (Vec<(AABB, T)>, usize) {
+    let centroids: Vec<Point3> = objects.iter().map(|(bbox, _)| bbox.centroid()).collect();
+    let (lo, hi) = centroids
+        .iter()
+        .skip(1)
+        .fold((centroids[0], centroids[0]), |(lo, hi), c| {
+            (
+                Point3::new(lo.x.min(c.x), lo.y.min(c.y), lo.z.min(c.z)),
+                Point3::new(hi.x.max(c.x), hi.y.max(c.y), hi.z.max(c.z)),
+            )
+        });
+
+    let bin_of = |c: Point3, axis: usize| {
+        let t = (c[axis] - lo[axis]) / (hi[axis] - lo[axis]);
+        ((t * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+    };
+
+    // (cost, axis, last bin on the left)
+    let mut best = None as Option<(f64, usize, usize)>;
+    for axis in 0..3 {
+        if hi[axis] - lo[axis] <= 0.0 {
+            continue;
+        }
+        let mut bins = [(0, None as Option<AABB>); SAH_BINS];
+        for ((bbox, _), c) in objects.iter().zip(&centroids) {
+            let bin = &mut bins[bin_of(*c, axis)];
+            bin.0 += 1;
+            bin.1 = Some(bin.1.map_or(*bbox, |b| surrounding_box(b, *bbox)));
+        }
+
+        // sweep from the right to get the cost of everything past each plane
+        let mut right_cost = [0.0; SAH_BINS];
+        let (mut count, mut bbox) = (0, None as Option<AABB>);
+        for i in (1..SAH_BINS).rev() {
+            count += bins[i].0;
+            bbox = match (bbox, bins[i].1) {
+                (Some(a), Some(b)) => Some(surrounding_box(a, b)),
+                (a, b) => a.or(b),
+            };
+            right_cost[i] = bbox.map_or(0.0, |b| b.surface_area()) * count as f64;
+        }
+        let (mut count, mut bbox) = (0, None as Option<AABB>);
+        for i in 0..SAH_BINS - 1 {
+            count += bins[i].0;
+            bbox = match (bbox, bins[i].1) {
+                (Some(a), Some(b)) => Some(surrounding_box(a, b)),
+                (a, b) => a.or(b),
+            };
+            if count == 0 || count == objects.len() {
+                continue;
+            }
+            let cost = bbox.map_or(0.0, |b| b.surface_area()) * count as f64 + right_cost[i + 1];
+            if best.map_or(true, |(c, _, _)| cost < c) {
+                best = Some((cost, axis, i));
+            }
+        }
+    }
+
+    match best {
+        Some((_, axis, last)) => {
+            let (left, right): (Vec<_>, Vec<_>) = std::mem::take(objects)
+                .into_iter()
+                .partition(|(bbox, _)| bin_of(bbox.centroid(), axis) <= last);
+            *objects = left;
+            (right, axis)
+        }
+        None => (objects.split_off(objects.len() / 2), 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::{Cuboid, XZrect};
+
+    /// A dense cluster of small spheres and a few large ones far apart.
+    fn uneven_scene() -> Vec<Arc<dyn Object>> {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::ones()));
+        let mut objects: Vec<Arc<dyn Object>> = vec![];
+        for i in 0..100 {
+            let center = Point3::new((i % 10) as f64 * 0.1, (i / 10) as f64 * 0.1, 0.0);
+            objects.push(Arc::new(Sphere::new(center, 0.04, mat.clone())));
+        }
+        for i in 0..4 {
+            let center = Point3::new(20.0 * i as f64, 10.0, -30.0);
+            objects.push(Arc::new(Sphere::new(center, 3.0, mat.clone())));
+        }
+        objects
+    }
+
+    #[test]
+    fn test_sah_stats() {
+        let (_, median) = BvhNode::init_with(uneven_scene(), 0.0, 1.0, true, SplitMethod::Median);
+        let (_, sah) = BvhNode::init_with(uneven_scene(), 0.0, 1.0, true, SplitMethod::Sah);
+        for stats in &[median, sah] {
+            assert_eq!(stats.leaf_count, 104);
+            assert_eq!(stats.node_count, 103);
+            assert!(stats.max_depth >= 7);
+        }
+        assert!(sah.cost < median.cost);
+    }
+
+    #[test]
+    fn test_sah_hits() {
+        let mut list = HittableList::new(true);
+        for ob in uneven_scene() {
+            list.push(ob);
+        }
+        let (tree, _) = BvhNode::init_with(uneven_scene(), 0.0, 1.0, true, SplitMethod::Sah);
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let target = Point3::new(rng.gen_range(-1.0, 2.0), rng.gen_range(-1.0, 2.0), 0.0);
+            let ray = Ray::new(
+                Point3::new(0.5, 0.5, 5.0),
+                target - Point3::new(0.5, 0.5, 5.0),
+                0.0,
+            );
+            let expected = list.hit(&ray, 0.001, 100.0).map(|rec| rec.t);
+            assert_eq!(tree.hit(&ray, 0.001, 100.0).map(|rec| rec.t), expected);
+        }
+    }
+
+    #[test]
+    fn test_occluded_matches_hit() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::ones()));
+        let mut objects = uneven_scene();
+        objects.push(Arc::new(XZrect::new(
+            -2.0,
+            2.0,
+            -2.0,
+            2.0,
+            -1.0,
+            mat.clone(),
+        )));
+        objects.push(Arc::new(Cuboid::new(
+            Point3::new(1.2, 0.0, 0.5),
+            Point3::new(1.6, 0.5, 0.9),
+            mat,
+        )));
+        let tree = BvhNode::init(objects, 0.0, 1.0, true);
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let orig = Point3::new(0.5, 0.5, 3.0);
+            let target = Point3::new(
+                rng.gen_range(-2.0, 2.0),
+                rng.gen_range(-2.0, 2.0),
+                rng.gen_range(-1.0, 1.0),
+            );
+            // shadow ray that stops just short of the target
+            let ray = Ray::new(orig, target - orig, 0.0);
+            let expected = tree.hit(&ray, 0.001, 0.999).is_some();
+            assert_eq!(tree.occluded(&ray, 0.001, 0.999), expected);
+        }
+    }
+}
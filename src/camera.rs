@@ -1,6 +1,7 @@
 pub use crate::ray::Ray;
 pub use crate::vec3::Point3;
 pub use crate::vec3::Vec3;
+use rand::Rng;
 
 #[derive(Copy, Clone)]
 pub struct Camera {
@@ -12,6 +13,8 @@ pub struct Camera {
     pub v: Vec3,
     pub w: Vec3,
     pub lens_radius: f64,
+    pub time0: f64,
+    pub time1: f64,
 }
 
 pub fn degrees_to_radians(degrees: f64) -> f64 {
@@ -19,6 +22,7 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from: Point3,
         look_at: Point3,
@@ -27,6 +31,8 @@ impl Camera {
         aspect_ratio: f64,
         aperture: f64,
         focus_dist: f64,
+        time0: f64,
+        time1: f64,
     ) -> Self {
         let theta = degrees_to_radians(vfov);
         let h = (theta / 2.0).tan();
@@ -49,17 +55,23 @@ impl Camera {
             u: su,
             v: sv,
             lens_radius: aperture / 2.0,
+            time0,
+            time1,
         }
     }
 
+    /// Ray through (`s`, `t`) on the viewport, sent at a random moment while
+    /// the shutter is open between `time0` and `time1`.
     pub fn get_ray(&self, s: f64, t: f64) -> Ray {
         let rd = crate::vec3::random_in_unit_disk() * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
+        let shutter: f64 = rand::thread_rng().gen();
         Ray::new(
             self.origin + offset,
             self._lower_left_corner + self.horizontal * s + self.vertical * t
                 - self.origin
                 - offset,
+            self.time0 + (self.time1 - self.time0) * shutter,
         )
     }
 }
@@ -0,0 +1,202 @@
+pub use crate::object::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Constructive solid geometry over two closed objects. The crossings of
+/// both children are merged into one interval list along the ray, and only
+/// those where the combined solid changes from outside to inside (or back)
+/// are kept. Since the result is again a closed object, `Csg` nodes can be
+/// nested and placed in a `BvhNode` like any other object; nested nodes hand
+/// their crossings up directly, so each child is walked once per ray.
+pub struct Csg {
+    op: CsgOp,
+    left: Arc<dyn Object>,
+    right: Arc<dyn Object>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self { op, left, right }
+    }
+
+    pub fn union(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(CsgOp::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(CsgOp::Intersection, left, right)
+    }
+
+    /// `left` with `right` carved out of it.
+    pub fn difference(left: Arc<dyn Object>, right: Arc<dyn Object>) -> Self {
+        Self::new(CsgOp::Difference, left, right)
+    }
+}
+
+impl Object for Csg {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.crossings(ray)
+            .into_iter()
+            .find(|rec| rec.t > t_min && rec.t < t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        let left = self.left.bounding_box(t0, t1)?;
+        match self.op {
+            CsgOp::Union => Some(surrounding_box(left, self.right.bounding_box(t0, t1)?)),
+            CsgOp::Intersection => match self.right.bounding_box(t0, t1) {
+                Some(right) => {
+                    let small = Point3::new(
+                        left.min_p.x.max(right.min_p.x),
+                        left.min_p.y.max(right.min_p.y),
+                        left.min_p.z.max(right.min_p.z),
+                    );
+                    let big = Point3::new(
+                        left.max_p.x.min(right.max_p.x).max(small.x),
+                        left.max_p.y.min(right.max_p.y).max(small.y),
+                        left.max_p.z.min(right.max_p.z).max(small.z),
+                    );
+                    Some(AABB::new(small, big))
+                }
+                None => Some(left),
+            },
+            CsgOp::Difference => Some(left),
+        }
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+
+    /// Surface crossings of the combined solid along the line of `ray`.
+    fn crossings(&self, ray: &Ray) -> Vec<HitRecord> {
+        let mut events: Vec<(HitRecord, bool)> = self
+            .left
+            .crossings(ray)
+            .into_iter()
+            .map(|rec| (rec, true))
+            .chain(
+                self.right
+                    .crossings(ray)
+                    .into_iter()
+                    .map(|rec| (rec, false)),
+            )
+            .collect();
+        events.sort_by(|a, b| a.0.t.partial_cmp(&b.0.t).unwrap());
+
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut res = vec![];
+        for (mut rec, from_left) in events {
+            let was_inside = self.op.inside(in_left, in_right);
+            if from_left {
+                in_left = rec.front_face;
+            } else {
+                in_right = rec.front_face;
+            }
+            let is_inside = self.op.inside(in_left, in_right);
+            if was_inside != is_inside {
+                // the normal already faces the ray; only the side changes,
+                // e.g. leaving the carved-out part means entering the result
+                rec.front_face = is_inside;
+                res.push(rec);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn sphere(x: f64, r: f64) -> Arc<dyn Object> {
+        Arc::new(Sphere::new(
+            Point3::new(x, 0.0, 0.0),
+            r,
+            Arc::new(Lambertian::new(&Color::ones())),
+        ))
+    }
+
+    fn along_x() -> Ray {
+        Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0)
+    }
+
+    #[test]
+    fn test_lens() {
+        let lens = Csg::intersection(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let ts: Vec<f64> = lens.crossings(&along_x()).iter().map(|rec| rec.t).collect();
+        assert_eq!(ts.len(), 2);
+        assert!((ts[0] - 9.5).abs() < 1e-9);
+        assert!((ts[1] - 10.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_difference_flips_side() {
+        // hollow shell: the second crossing enters the shell from the cavity
+        let shell = Csg::difference(sphere(0.0, 2.0), sphere(0.0, 1.0));
+        let recs = shell.crossings(&along_x());
+        assert_eq!(recs.len(), 4);
+        let sides: Vec<bool> = recs.iter().map(|rec| rec.front_face).collect();
+        assert_eq!(sides, vec![true, false, true, false]);
+        // normal on the inner wall points into the cavity, against the ray
+        assert_eq!(recs[2].normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        let nested = Csg::union(Arc::new(shell), sphere(0.0, 0.5));
+        assert_eq!(nested.crossings(&along_x()).len(), 6);
+    }
+
+    /// Sphere that counts how often it is asked for a hit.
+    struct Counted(Sphere, AtomicUsize);
+
+    impl Object for Counted {
+        fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+            self.1.fetch_add(1, AtomicOrdering::Relaxed);
+            self.0.hit(ray, t_min, t_max)
+        }
+
+        fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+            self.0.bounding_box(t0, t1)
+        }
+
+        fn get_background(&self, t: f64) -> Color {
+            self.0.get_background(t)
+        }
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let leaf = Arc::new(Counted(
+            Sphere::new(
+                Point3::zero(),
+                1.0,
+                Arc::new(Lambertian::new(&Color::ones())),
+            ),
+            AtomicUsize::new(0),
+        ));
+        let mut solid: Arc<dyn Object> = leaf.clone();
+        for i in 0..20 {
+            solid = Arc::new(Csg::union(solid, sphere(i as f64 + 2.0, 0.25)));
+        }
+        let rec = solid.hit(&along_x(), 0.001, 100.0).unwrap();
+        assert!((rec.t - 9.0).abs() < 1e-9);
+        // two crossings and the miss after them, not one walk per level
+        assert_eq!(leaf.1.load(AtomicOrdering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,439 @@
+pub use crate::object::*;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CurveType {
+    /// Flat strip that always faces the incoming ray, for thin fibers.
+    Ribbon,
+    /// Round tube, for cables and close-up hair.
+    Cylinder,
+}
+
+fn bezier(cp: &[Point3; 4], u: f64) -> Point3 {
+    let s = 1.0 - u;
+    cp[0] * (s * s * s)
+        + cp[1] * (3.0 * s * s * u)
+        + cp[2] * (3.0 * s * u * u)
+        + cp[3] * (u * u * u)
+}
+
+fn bezier_derivative(cp: &[Point3; 4], u: f64) -> Vec3 {
+    let s = 1.0 - u;
+    ((cp[1] - cp[0]) * (s * s) + (cp[2] - cp[1]) * (2.0 * s * u) + (cp[3] - cp[2]) * (u * u)) * 3.0
+}
+
+/// Control points of both halves of the curve, sharing the middle one.
+fn split_half(cp: &[Point3; 4]) -> [Point3; 7] {
+    let m01 = (cp[0] + cp[1]) * 0.5;
+    let m12 = (cp[1] + cp[2]) * 0.5;
+    let m23 = (cp[2] + cp[3]) * 0.5;
+    let a = (m01 + m12) * 0.5;
+    let b = (m12 + m23) * 0.5;
+    let mid = (a + b) * 0.5;
+    [cp[0], m01, a, mid, b, m23, cp[3]]
+}
+
+/// Control points of the piece of the curve between `u0` and `u1`.
+fn sub_curve(cp: &[Point3; 4], u0: f64, u1: f64) -> [Point3; 4] {
+    // de Casteljau at u1 keeps [0, u1], then at u0 / u1 keeps the rest
+    let split = |cp: &[Point3; 4], u: f64, left: bool| {
+        let lerp = |a: Point3, b: Point3| a * (1.0 - u) + b * u;
+        let (a, b, c) = (lerp(cp[0], cp[1]), lerp(cp[1], cp[2]), lerp(cp[2], cp[3]));
+        let (d, e) = (lerp(a, b), lerp(b, c));
+        let f = lerp(d, e);
+        if left {
+            [cp[0], a, d, f]
+        } else {
+            [f, e, c, cp[3]]
+        }
+    };
+    let head = split(cp, u1, true);
+    if u1 > 0.0 {
+        split(&head, u0 / u1, false)
+    } else {
+        [cp[0]; 4]
+    }
+}
+
+/// Cubic Bézier curve swept with a width that varies linearly from
+/// `width0` to `width1`. Rays are intersected by recursively splitting the
+/// curve in a frame looking down the ray until the pieces are nearly
+/// straight (after pbrt). Hits carry the curve tangent, with `u` running
+/// along the curve and `v` across it.
+pub struct Curve {
+    cp: [Point3; 4],
+    width0: f64,
+    width1: f64,
+    kind: CurveType,
+    mp: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Curve {
+    pub fn new(
+        cp: [Point3; 4],
+        width0: f64,
+        width1: f64,
+        kind: CurveType,
+        mp: Arc<dyn Material>,
+    ) -> Self {
+        // exact extent of the curve: the endpoints plus any axis extrema
+        let mut small = cp[0];
+        let mut big = cp[0];
+        let mut include = |p: Point3| {
+            small = Point3::new(small.x.min(p.x), small.y.min(p.y), small.z.min(p.z));
+            big = Point3::new(big.x.max(p.x), big.y.max(p.y), big.z.max(p.z));
+        };
+        include(cp[3]);
+        for axis in 0..3 {
+            // derivative / 3 = a u^2 + b u + c
+            let (p0, p1, p2, p3) = (cp[0][axis], cp[1][axis], cp[2][axis], cp[3][axis]);
+            let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+            let b = 2.0 * (p0 - 2.0 * p1 + p2);
+            let c = p1 - p0;
+            let roots = if a.abs() < 1e-12 {
+                if b.abs() < 1e-12 {
+                    vec![]
+                } else {
+                    vec![-c / b]
+                }
+            } else {
+                let disc = b * b - 4.0 * a * c;
+                if disc < 0.0 {
+                    vec![]
+                } else {
+                    let sq = disc.sqrt();
+                    vec![(-b - sq) / (2.0 * a), (-b + sq) / (2.0 * a)]
+                }
+            };
+            for u in roots {
+                if u > 0.0 && u < 1.0 {
+                    include(bezier(&cp, u));
+                }
+            }
+        }
+        let r = width0.max(width1) / 2.0;
+
+        Self {
+            cp,
+            width0,
+            width1,
+            kind,
+            mp,
+            bbox: AABB::new(small - r, big + r),
+        }
+    }
+
+    /// The curve cut into `n` pieces of equal parameter length. Long or
+    /// strongly bent strands fit a `BvhNode` much better this way.
+    pub fn split(&self, n: usize) -> Vec<Curve> {
+        (0..n)
+            .map(|i| {
+                let (u0, u1) = (i as f64 / n as f64, (i + 1) as f64 / n as f64);
+                Curve::new(
+                    sub_curve(&self.cp, u0, u1),
+                    self.width(u0),
+                    self.width(u1),
+                    self.kind,
+                    self.mp.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn width(&self, u: f64) -> f64 {
+        self.width0 * (1.0 - u) + self.width1 * u
+    }
+
+    /// Closest crossing of the origin by the curve `cp`, given in the ray's
+    /// frame, as (distance along the ray, u, v).
+    fn recursive_hit(
+        &self,
+        cp: &[Point3; 4],
+        u0: f64,
+        u1: f64,
+        depth: u32,
+        z_min: f64,
+        mut z_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let r = self.width(u0).max(self.width(u1)) / 2.0;
+        let lo = |axis: usize| {
+            cp.iter()
+                .map(|p| p[axis])
+                .fold(std::f64::INFINITY, f64::min)
+                - r
+        };
+        let hi = |axis: usize| {
+            cp.iter()
+                .map(|p| p[axis])
+                .fold(-std::f64::INFINITY, f64::max)
+                + r
+        };
+        if lo(0) > 0.0
+            || hi(0) < 0.0
+            || lo(1) > 0.0
+            || hi(1) < 0.0
+            || hi(2) < z_min
+            || lo(2) > z_max
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let halves = split_half(cp);
+            let u_mid = (u0 + u1) / 2.0;
+            let left = [halves[0], halves[1], halves[2], halves[3]];
+            let right = [halves[3], halves[4], halves[5], halves[6]];
+            let first = self.recursive_hit(&left, u0, u_mid, depth - 1, z_min, z_max);
+            if let Some((z, _, _)) = first {
+                z_max = z;
+            }
+            return self
+                .recursive_hit(&right, u_mid, u1, depth - 1, z_min, z_max)
+                .or(first);
+        }
+
+        // the origin must lie between the planes cutting off the segment ends
+        let edge = (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        if edge < 0.0 {
+            return None;
+        }
+        let edge = (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if edge < 0.0 {
+            return None;
+        }
+
+        // closest point on the (nearly straight) segment
+        let seg = cp[3] - cp[0];
+        let denom = seg.x * seg.x + seg.y * seg.y;
+        if denom < 1e-300 {
+            return None;
+        }
+        let w = ((-cp[0].x * seg.x - cp[0].y * seg.y) / denom)
+            .max(0.0)
+            .min(1.0);
+        let u = u0 + w * (u1 - u0);
+        let hit_width = self.width(u);
+        let pc = bezier(cp, w);
+        let dist2 = pc.x * pc.x + pc.y * pc.y;
+        if dist2 > hit_width * hit_width / 4.0 || pc.z < z_min || pc.z > z_max {
+            return None;
+        }
+
+        let dpcdw = bezier_derivative(cp, w);
+        let side = dpcdw.x * -pc.y + pc.x * dpcdw.y;
+        let offset = dist2.sqrt() / hit_width;
+        let v = if side > 0.0 {
+            0.5 + offset
+        } else {
+            0.5 - offset
+        };
+        Some((pc.z, u, v))
+    }
+}
+
+impl Object for Curve {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let dir_length = ray.dir.length();
+        let dir = ray.dir / dir_length;
+
+        // frame looking down the ray
+        let helper = if dir.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let x_axis = Vec3::cross(helper, dir).unit();
+        let y_axis = Vec3::cross(dir, x_axis);
+        let to_ray = |p: Point3| {
+            let q = p - ray.orig;
+            Point3::new(q * x_axis, q * y_axis, q * dir)
+        };
+        let cp = [
+            to_ray(self.cp[0]),
+            to_ray(self.cp[1]),
+            to_ray(self.cp[2]),
+            to_ray(self.cp[3]),
+        ];
+
+        // split until the pieces deviate from a line by about 5% of the width
+        let l0 = (0..2)
+            .map(|i| {
+                let d = cp[i] - cp[i + 1] * 2.0 + cp[i + 2];
+                d.x.abs().max(d.y.abs()).max(d.z.abs())
+            })
+            .fold(0.0, f64::max);
+        let eps = self.width0.max(self.width1) * 0.05;
+        let depth = if l0 > 0.0 && eps > 0.0 {
+            ((std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() / 2.0)
+                .ceil()
+                .max(0.0)
+                .min(10.0) as u32
+        } else {
+            0
+        };
+
+        let (z, u, v) =
+            self.recursive_hit(&cp, 0.0, 1.0, depth, t_min * dir_length, t_max * dir_length)?;
+        // a repeated control point leaves the derivative zero at that end
+        let mut tangent = bezier_derivative(&self.cp, u);
+        if tangent.squared_length() < 1e-24 {
+            tangent = self.cp[3] - self.cp[0];
+            if tangent.squared_length() < 1e-24 {
+                return None;
+            }
+        }
+        let tangent = tangent.unit();
+        let center = bezier(&self.cp, u);
+        let perpendicular = |w: Vec3| w - tangent * (w * tangent);
+
+        let (tin, outward_normal) = match self.kind {
+            CurveType::Ribbon => {
+                // looking straight down the curve there is no side to face
+                let normal = -perpendicular(dir);
+                if normal.squared_length() < 1e-12 {
+                    return None;
+                }
+                (z / dir_length, normal.unit())
+            }
+            CurveType::Cylinder => {
+                // enter the tube around the closest point of the center line
+                let radius = self.width(u) / 2.0;
+                let oc = perpendicular(ray.orig - center);
+                let d = perpendicular(ray.dir);
+                let a = d * d;
+                let half_b = oc * d;
+                let disc = half_b * half_b - a * (oc * oc - radius * radius);
+                if a < 1e-300 || disc < 0.0 {
+                    return None;
+                }
+                let sq = disc.sqrt();
+                let tin = [(-half_b - sq) / a, (-half_b + sq) / a]
+                    .iter()
+                    .copied()
+                    .find(|&t| t > t_min && t < t_max)?;
+                (tin, perpendicular(ray.at(tin) - center).unit())
+            }
+        };
+
+        let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_uv((u, v));
+        rec.tangent = Some(tangent);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn straight(kind: CurveType) -> Curve {
+        Curve::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(3.0, 0.0, 0.0),
+            ],
+            0.2,
+            0.2,
+            kind,
+            Arc::new(Lambertian::new(&Color::ones())),
+        )
+    }
+
+    fn down(x: f64, y: f64) -> Ray {
+        Ray::new(Point3::new(x, y, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0)
+    }
+
+    #[test]
+    fn test_ribbon() {
+        let ribbon = straight(CurveType::Ribbon);
+        let rec = ribbon.hit(&down(1.5, 0.05), 0.001, 100.0).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-9);
+        assert!((rec.u - 0.5).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((rec.tangent.unwrap() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(ribbon.hit(&down(1.5, 0.15), 0.001, 100.0).is_none());
+        assert!(ribbon.hit(&down(3.05, 0.0), 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_cylinder() {
+        let tube = straight(CurveType::Cylinder);
+        let rec = tube.hit(&down(1.0, 0.06), 0.001, 100.0).unwrap();
+        assert!((rec.p.z - 0.08).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.6, 0.8)).length() < 1e-9);
+
+        // from the side, the tube is entered at its radius
+        let ray = Ray::new(Point3::new(1.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = tube.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_degenerate() {
+        // looking down a straight ribbon there is nothing to face
+        let ribbon = straight(CurveType::Ribbon);
+        let ray = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        assert!(ribbon.hit(&ray, 0.001, 100.0).is_none());
+
+        // a repeated control point leaves the chord as the tangent
+        let kink = Curve::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(2.0, 0.0, 0.0),
+                Point3::new(3.0, 0.0, 0.0),
+            ],
+            0.2,
+            0.2,
+            CurveType::Ribbon,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let rec = kink.hit(&down(0.0, 0.0), 0.001, 100.0).unwrap();
+        assert!((rec.tangent.unwrap() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+
+        // and a curve collapsed to a point has no tangent at all
+        let dot = Curve::new(
+            [Point3::zero(); 4],
+            0.2,
+            0.2,
+            CurveType::Cylinder,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        assert!(dot.hit(&down(0.0, 0.0), 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_tight_box() {
+        let arch = Curve::new(
+            [
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(1.0, 0.0, 0.0),
+            ],
+            0.1,
+            0.1,
+            CurveType::Ribbon,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let bbox = arch.bounding_box(0.0, 1.0).unwrap();
+        assert!((bbox.max_p.y - 0.8).abs() < 1e-9);
+
+        // the pieces join up and cover the same curve
+        let pieces = arch.split(3);
+        assert!((pieces[0].cp[3] - bezier(&arch.cp, 1.0 / 3.0)).length() < 1e-9);
+        assert!((pieces[2].cp[0] - pieces[1].cp[3]).length() < 1e-9);
+        assert!((bezier(&pieces[1].cp, 0.5) - bezier(&arch.cp, 0.5)).length() < 1e-9);
+    }
+}
@@ -0,0 +1,990 @@
+use crate::bvh::*;
+use crate::camera::Camera;
+use crate::json::*;
+use crate::transform::*;
+use crate::triangle::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+const GLB_JSON: u32 = 0x4E4F_534A;
+const GLB_BIN: u32 = 0x004E_4942;
+
+const MODE_TRIANGLES: usize = 4;
+const MODE_TRIANGLE_STRIP: usize = 5;
+const MODE_TRIANGLE_FAN: usize = 6;
+
+const BYTE: usize = 5120;
+const UNSIGNED_BYTE: usize = 5121;
+const SHORT: usize = 5122;
+const UNSIGNED_SHORT: usize = 5123;
+const UNSIGNED_INT: usize = 5125;
+const FLOAT: usize = 5126;
+
+/// Bound on the node hierarchy depth, which also catches cyclic graphs.
+const MAX_DEPTH: usize = 256;
+
+/// Extensions that are understood well enough to be honored when a file
+/// lists them as required.
+const SUPPORTED_EXTENSIONS: [&str; 3] = [
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+];
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(String, std::io::Error),
+    Json(JsonError),
+    Image(usize, image::ImageError),
+    Invalid(String),
+    Unsupported(String),
+    Empty,
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(file, err) => write!(f, "cannot read {}: {}", file, err),
+            GltfError::Json(err) => write!(f, "{}", err),
+            GltfError::Image(index, err) => write!(f, "cannot decode image {}: {}", index, err),
+            GltfError::Invalid(msg) => write!(f, "invalid glTF: {}", msg),
+            GltfError::Unsupported(what) => write!(f, "unsupported glTF feature: {}", what),
+            GltfError::Empty => write!(f, "scene contains no triangles"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+fn invalid<S: Into<String>>(msg: S) -> GltfError {
+    GltfError::Invalid(msg.into())
+}
+
+pub struct GltfScene {
+    pub world: HittableList,
+    /// The perspective cameras of the scene, in node order.
+    pub cameras: Vec<Camera>,
+}
+
+/// Loads the default scene of a glTF 2.0 file, either a `.gltf` with
+/// embedded or external buffers and images, or a binary `.glb`. The node
+/// hierarchy is flattened into a list holding a single `BvhNode` over all
+/// triangles in world space, and perspective cameras are set up for
+/// `aspect_ratio`, the one of the image being rendered.
+///
+/// Metallic-roughness materials are mapped onto the closest `Material`:
+/// emissive ones become `DiffuseLight`, transmissive ones `Dielectric`,
+/// metallic ones `Metal` with a fuzz of roughness squared, and the rest
+/// `Lambertian` over the base color factor, texture and vertex colors.
+/// Without any emissive material the world gets a sky background.
+pub fn load_gltf<P: AsRef<Path>>(path: P, aspect_ratio: f64) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| GltfError::Io(path.display().to_string(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_gltf(&data, base_dir, aspect_ratio)
+}
+
+fn parse_gltf(data: &[u8], base_dir: &Path, aspect_ratio: f64) -> Result<GltfScene, GltfError> {
+    let (json, bin) = if data.starts_with(b"glTF") {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+    let text = std::str::from_utf8(json).map_err(|_| invalid("JSON is not UTF-8"))?;
+    let doc = Json::parse(text.trim_start_matches('\u{feff}')).map_err(GltfError::Json)?;
+
+    let version = doc
+        .get("asset")
+        .and_then(|asset| asset.get("version"))
+        .and_then(Json::as_str)
+        .ok_or_else(|| invalid("missing asset version"))?;
+    if !version.starts_with("2.") {
+        return Err(GltfError::Unsupported(format!("version {}", version)));
+    }
+    for ext in array(doc.get("extensionsRequired")) {
+        let name = ext.as_str().unwrap_or_default();
+        if !SUPPORTED_EXTENSIONS.contains(&name) {
+            return Err(GltfError::Unsupported(format!("extension {}", name)));
+        }
+    }
+
+    let mut loader = Loader {
+        doc: &doc,
+        base_dir,
+        aspect_ratio,
+        buffers: vec![],
+        images: HashMap::new(),
+        materials: HashMap::new(),
+        default_mat: Arc::new(Lambertian::new(&(Color::ones() * 0.8))),
+        has_lights: false,
+        triangles: vec![],
+        cameras: vec![],
+    };
+    loader.load_buffers(bin)?;
+    for root in loader.scene_roots()? {
+        loader.visit_node(root, Mat4::identity(), 0)?;
+    }
+    if loader.triangles.is_empty() {
+        return Err(GltfError::Empty);
+    }
+
+    // scenes without emissive materials are lit by the sky instead
+    let mut world = HittableList::new(loader.has_lights);
+    world.push(BvhNode::init(loader.triangles, 0.0, 1.0, true));
+    Ok(GltfScene {
+        world,
+        cameras: loader.cameras,
+    })
+}
+
+/// JSON and (optional) binary chunk of a GLB container.
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let word = |at: usize| -> Result<u32, GltfError> {
+        data.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid("truncated GLB"))
+    };
+    let version = word(4)?;
+    if version != 2 {
+        return Err(GltfError::Unsupported(format!("GLB version {}", version)));
+    }
+    let length = (word(8)? as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos + 8 <= length {
+        let chunk_length = word(pos)? as usize;
+        let chunk = data
+            .get(pos + 8..pos + 8 + chunk_length)
+            .ok_or_else(|| invalid("truncated GLB chunk"))?;
+        match word(pos + 4)? {
+            GLB_JSON if json.is_none() => json = Some(chunk),
+            GLB_BIN if bin.is_none() => bin = Some(chunk),
+            _ => {}
+        }
+        pos += 8 + chunk_length;
+    }
+    Ok((json.ok_or_else(|| invalid("GLB has no JSON chunk"))?, bin))
+}
+
+/// Items of a JSON array, empty for anything else.
+fn array(value: Option<&Json>) -> &[Json] {
+    value.and_then(Json::as_array).unwrap_or(&[])
+}
+
+fn indices(value: Option<&Json>) -> Vec<usize> {
+    array(value).iter().filter_map(Json::as_usize).collect()
+}
+
+/// Decodes standard or URL-safe base64, ignoring whitespace and padding.
+fn decode_base64(src: &str) -> Option<Vec<u8>> {
+    let mut res = Vec::with_capacity(src.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in src.bytes() {
+        let val = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        acc = ((acc << 6) | val as u32) & 0xFF_FFFF;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            res.push((acc >> bits) as u8);
+        }
+    }
+    Some(res)
+}
+
+/// Undoes the `%XX` escapes of a relative URI.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                res.push(b);
+                i += 3;
+            }
+            None => {
+                res.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+/// Local transform of a node, given either as a column-major `matrix` or
+/// as translation, rotation (a quaternion) and scale.
+fn local_transform(node: &Json) -> Result<Mat4, GltfError> {
+    let numbers = |key: &str, len: usize| -> Result<Option<Vec<f64>>, GltfError> {
+        match node.get(key) {
+            Some(value) => value
+                .as_f64_vec()
+                .filter(|v| v.len() == len)
+                .map(Some)
+                .ok_or_else(|| invalid(format!("node {} must have {} numbers", key, len))),
+            None => Ok(None),
+        }
+    };
+
+    if let Some(a) = numbers("matrix", 16)? {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = a[j * 4 + i];
+            }
+        }
+        return Ok(Mat4::new(m));
+    }
+
+    let t = numbers("translation", 3)?.unwrap_or_else(|| vec![0.0; 3]);
+    let r = numbers("rotation", 4)?.unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+    let s = numbers("scale", 3)?.unwrap_or_else(|| vec![1.0; 3]);
+    Ok(Mat4::translation(Vec3::new(t[0], t[1], t[2]))
+        * quaternion_rotation(&r)
+        * Mat4::scaling(Vec3::new(s[0], s[1], s[2])))
+}
+
+/// Rotation matrix of the quaternion `(x, y, z, w)`.
+fn quaternion_rotation(q: &[f64]) -> Mat4 {
+    let len = q.iter().map(|c| c * c).sum::<f64>().sqrt();
+    if len < 1e-12 {
+        return Mat4::identity();
+    }
+    let (x, y, z, w) = (q[0] / len, q[1] / len, q[2] / len, q[3] / len);
+    Mat4::new([
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y - z * w),
+            2.0 * (x * z + y * w),
+            0.0,
+        ],
+        [
+            2.0 * (x * y + z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z - x * w),
+            0.0,
+        ],
+        [
+            2.0 * (x * z - y * w),
+            2.0 * (y * z + x * w),
+            1.0 - 2.0 * (x * x + y * y),
+            0.0,
+        ],
+        [0.0, 0.0, 0.0, 1.0],
+    ])
+}
+
+/// glTF base color: the factor times the texture times the vertex color.
+struct BaseColor {
+    factor: Color,
+    image: Option<Arc<ImageTexture>>,
+}
+
+impl Texture for BaseColor {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        match &self.image {
+            Some(image) => self.factor.elemul(image.value(u, v, p)),
+            None => self.factor,
+        }
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        let base = self.value(rec.u, rec.v, &rec.p);
+        match rec.color {
+            Some(c) => base.elemul(c),
+            None => base,
+        }
+    }
+}
+
+struct Loader<'a> {
+    doc: &'a Json,
+    base_dir: &'a Path,
+    aspect_ratio: f64,
+    buffers: Vec<Vec<u8>>,
+    images: HashMap<usize, Arc<ImageTexture>>,
+    materials: HashMap<usize, Arc<dyn Material>>,
+    default_mat: Arc<dyn Material>,
+    has_lights: bool,
+    triangles: Vec<Arc<dyn Object>>,
+    cameras: Vec<Camera>,
+}
+
+impl<'a> Loader<'a> {
+    /// Item `index` of the top-level array `kind`, e.g. `nodes[3]`.
+    fn item(&self, kind: &str, index: usize) -> Result<&'a Json, GltfError> {
+        array(self.doc.get(kind))
+            .get(index)
+            .ok_or_else(|| invalid(format!("{}[{}] does not exist", kind, index)))
+    }
+
+    /// Contents of a `data:` URI, or of a file relative to the glTF file.
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, GltfError> {
+        if let Some(rest) = uri.strip_prefix("data:") {
+            let comma = rest
+                .find(',')
+                .ok_or_else(|| invalid("malformed data URI"))?;
+            if !rest[..comma].ends_with(";base64") {
+                return Err(GltfError::Unsupported("data URI without base64".to_owned()));
+            }
+            return decode_base64(&rest[comma + 1..])
+                .ok_or_else(|| invalid("malformed base64 in data URI"));
+        }
+        let path = self.base_dir.join(percent_decode(uri));
+        std::fs::read(&path).map_err(|e| GltfError::Io(path.display().to_string(), e))
+    }
+
+    fn load_buffers(&mut self, bin: Option<&[u8]>) -> Result<(), GltfError> {
+        for (i, buffer) in array(self.doc.get("buffers")).iter().enumerate() {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => self.read_uri(uri)?,
+                // the first buffer of a GLB lives in its binary chunk
+                None if i == 0 && bin.is_some() => bin.unwrap().to_vec(),
+                None => return Err(invalid(format!("buffer {} has no data", i))),
+            };
+            let length = buffer
+                .get("byteLength")
+                .and_then(Json::as_usize)
+                .ok_or_else(|| invalid(format!("buffer {} has no byteLength", i)))?;
+            if data.len() < length {
+                return Err(invalid(format!(
+                    "buffer {} is shorter than its byteLength",
+                    i
+                )));
+            }
+            self.buffers.push(data);
+        }
+        Ok(())
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<&[u8], GltfError> {
+        let view = self.item("bufferViews", index)?;
+        let buffer = view
+            .get("buffer")
+            .and_then(Json::as_usize)
+            .and_then(|b| self.buffers.get(b))
+            .ok_or_else(|| invalid(format!("buffer view {} has no buffer", index)))?;
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view
+            .get("byteLength")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid(format!("buffer view {} has no byteLength", index)))?;
+        offset
+            .checked_add(length)
+            .and_then(|end| buffer.get(offset..end))
+            .ok_or_else(|| invalid(format!("buffer view {} exceeds its buffer", index)))
+    }
+
+    /// Elements of accessor `index` as flat `f64`s, with normalized integers
+    /// mapped to [0, 1] or [-1, 1], and the number of components per element.
+    fn read_accessor(&self, index: usize) -> Result<(Vec<f64>, usize), GltfError> {
+        let accessor = self.item("accessors", index)?;
+        let err = |msg: &str| invalid(format!("accessor {}: {}", index, msg));
+        if accessor.get("sparse").is_some() {
+            return Err(GltfError::Unsupported(format!("sparse accessor {}", index)));
+        }
+
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| err("missing count"))?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(err("unknown type")),
+        };
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .unwrap_or(0);
+        let size = match component_type {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            UNSIGNED_INT | FLOAT => 4,
+            _ => return Err(err("unknown componentType")),
+        };
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+
+        let element_size = size * components;
+
+        let view_index = match accessor.get("bufferView").and_then(Json::as_usize) {
+            Some(view_index) => view_index,
+            // an accessor without a buffer view is all zeros; it may be no
+            // bigger than the buffers, so a tiny file can't ask for a huge
+            // allocation
+            None => {
+                let buffered: usize = self.buffers.iter().map(Vec::len).sum();
+                if count
+                    .checked_mul(element_size)
+                    .map_or(true, |bytes| bytes > buffered)
+                {
+                    return Err(err("count is too large"));
+                }
+                return Ok((vec![0.0; count * components], components));
+            }
+        };
+        let data = self.buffer_view(view_index)?;
+        let stride = match self
+            .item("bufferViews", view_index)?
+            .get("byteStride")
+            .and_then(Json::as_usize)
+        {
+            None | Some(0) => element_size,
+            Some(stride) if stride >= element_size && stride % 4 == 0 => stride,
+            Some(_) => return Err(err("byteStride is too small or not a multiple of 4")),
+        };
+        let offset = accessor
+            .get("byteOffset")
+            .and_then(Json::as_usize)
+            .unwrap_or(0);
+        // offset + (count - 1) * stride + element_size, which comes from the
+        // file and so must not overflow
+        let end = if count > 0 {
+            (count - 1)
+                .checked_mul(stride)
+                .and_then(|x| x.checked_add(offset))
+                .and_then(|x| x.checked_add(element_size))
+        } else {
+            Some(0)
+        };
+        if end.map_or(true, |end| end > data.len()) {
+            return Err(err("elements exceed the buffer view"));
+        }
+
+        // elements don't overlap, so this is bounded by the view's length
+        let mut res = Vec::with_capacity(
+            count
+                .checked_mul(components)
+                .ok_or_else(|| err("count is too large"))?,
+        );
+        for i in 0..count {
+            for c in 0..components {
+                let at = offset + i * stride + c * size;
+                let b = &data[at..at + size];
+                let value = match component_type {
+                    BYTE if normalized => (b[0] as i8 as f64 / 127.0).max(-1.0),
+                    BYTE => b[0] as i8 as f64,
+                    UNSIGNED_BYTE if normalized => b[0] as f64 / 255.0,
+                    UNSIGNED_BYTE => b[0] as f64,
+                    SHORT if normalized => {
+                        (i16::from_le_bytes([b[0], b[1]]) as f64 / 32767.0).max(-1.0)
+                    }
+                    SHORT => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    UNSIGNED_SHORT if normalized => {
+                        u16::from_le_bytes([b[0], b[1]]) as f64 / 65535.0
+                    }
+                    UNSIGNED_SHORT => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    UNSIGNED_INT => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                };
+                res.push(value);
+            }
+        }
+        Ok((res, components))
+    }
+
+    /// `read_accessor` for a use that only makes sense with one of `types`,
+    /// such as VEC3 positions, so a mistyped accessor is not misread.
+    fn read_typed(
+        &self,
+        index: usize,
+        semantic: &str,
+        types: &[&str],
+    ) -> Result<(Vec<f64>, usize), GltfError> {
+        let ty = self
+            .item("accessors", index)?
+            .get("type")
+            .and_then(Json::as_str);
+        if !ty.map_or(false, |ty| types.contains(&ty)) {
+            return Err(invalid(format!(
+                "accessor {} has the wrong type for {}",
+                index, semantic
+            )));
+        }
+        self.read_accessor(index)
+    }
+
+    /// Root nodes of the default scene. Files without scenes get every node
+    /// that is nobody's child.
+    fn scene_roots(&self) -> Result<Vec<usize>, GltfError> {
+        if array(self.doc.get("scenes")).is_empty() {
+            let nodes = array(self.doc.get("nodes"));
+            let mut is_child = vec![false; nodes.len()];
+            for node in nodes {
+                for child in indices(node.get("children")) {
+                    if child < nodes.len() {
+                        is_child[child] = true;
+                    }
+                }
+            }
+            return Ok((0..nodes.len()).filter(|&i| !is_child[i]).collect());
+        }
+        let scene = self.doc.get("scene").and_then(Json::as_usize).unwrap_or(0);
+        Ok(indices(self.item("scenes", scene)?.get("nodes")))
+    }
+
+    fn visit_node(&mut self, index: usize, parent: Mat4, depth: usize) -> Result<(), GltfError> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("node hierarchy is too deep or cyclic"));
+        }
+        let node = self.item("nodes", index)?;
+        let world = parent * local_transform(node)?;
+
+        if let Some(mesh) = node.get("mesh").and_then(Json::as_usize) {
+            self.add_mesh(mesh, &world)?;
+        }
+        if let Some(camera) = node.get("camera").and_then(Json::as_usize) {
+            self.add_camera(camera, &world)?;
+        }
+        for child in indices(node.get("children")) {
+            self.visit_node(child, world, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn add_camera(&mut self, index: usize, world: &Mat4) -> Result<(), GltfError> {
+        // orthographic cameras have no counterpart in `Camera`
+        let perspective = match self.item("cameras", index)?.get("perspective") {
+            Some(perspective) => perspective,
+            None => return Ok(()),
+        };
+        let yfov = perspective
+            .get("yfov")
+            .and_then(Json::as_f64)
+            .ok_or_else(|| invalid(format!("camera {} has no yfov", index)))?;
+
+        // cameras look down their local -z axis, with +y up
+        self.cameras.push(Camera::new(
+            world.transform_point(Point3::zero()),
+            world.transform_point(Point3::new(0.0, 0.0, -1.0)),
+            world.transform_vector(Vec3::new(0.0, 1.0, 0.0)),
+            yfov.to_degrees(),
+            self.aspect_ratio,
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+        ));
+        Ok(())
+    }
+
+    fn add_mesh(&mut self, index: usize, world: &Mat4) -> Result<(), GltfError> {
+        let normal_matrix = world.inverse().map(|inv| inv.transpose());
+        let primitives = self
+            .item("meshes", index)?
+            .get("primitives")
+            .and_then(Json::as_array)
+            .ok_or_else(|| invalid(format!("mesh {} has no primitives", index)))?;
+
+        for prim in primitives {
+            let mode = prim
+                .get("mode")
+                .and_then(Json::as_usize)
+                .unwrap_or(MODE_TRIANGLES);
+            if mode < MODE_TRIANGLES {
+                // points and lines have no surface to hit
+                continue;
+            }
+            let attributes = prim.get("attributes");
+            let attribute = |name: &str| {
+                attributes
+                    .and_then(|a| a.get(name))
+                    .and_then(Json::as_usize)
+            };
+
+            let position_accessor = attribute("POSITION").ok_or_else(|| {
+                invalid(format!("mesh {} has a primitive without POSITION", index))
+            })?;
+            let (data, _) = self.read_typed(position_accessor, "POSITION", &["VEC3"])?;
+            let positions: Vec<Point3> = data
+                .chunks_exact(3)
+                .map(|c| world.transform_point(Point3::new(c[0], c[1], c[2])))
+                .collect();
+            let n_vertices = positions.len();
+            let check = |len: usize, name: &str| {
+                if len == n_vertices {
+                    Ok(())
+                } else {
+                    Err(invalid(format!(
+                        "mesh {}: {} has {} elements for {} positions",
+                        index, name, len, n_vertices
+                    )))
+                }
+            };
+
+            let mut normals = None;
+            if let (Some(accessor), Some(m)) = (attribute("NORMAL"), normal_matrix) {
+                let (data, _) = self.read_typed(accessor, "NORMAL", &["VEC3"])?;
+                let n: Vec<Vec3> = data
+                    .chunks_exact(3)
+                    .map(|c| {
+                        // zero normals stay zero, so shading falls back to the
+                        // face normal there
+                        let n = m.transform_vector(Vec3::new(c[0], c[1], c[2]));
+                        if n.squared_length() > 0.0 {
+                            n.unit()
+                        } else {
+                            n
+                        }
+                    })
+                    .collect();
+                check(n.len(), "NORMAL")?;
+                normals = Some(n);
+            }
+            let mut uvs = None;
+            if let Some(accessor) = attribute("TEXCOORD_0") {
+                let (data, _) = self.read_typed(accessor, "TEXCOORD_0", &["VEC2"])?;
+                // glTF puts (0, 0) at the top-left corner of images
+                let uv: Vec<(f64, f64)> =
+                    data.chunks_exact(2).map(|c| (c[0], 1.0 - c[1])).collect();
+                check(uv.len(), "TEXCOORD_0")?;
+                uvs = Some(uv);
+            }
+            let mut colors = None;
+            if let Some(accessor) = attribute("COLOR_0") {
+                let (data, components) = self.read_typed(accessor, "COLOR_0", &["VEC3", "VEC4"])?;
+                let c: Vec<Color> = data
+                    .chunks_exact(components)
+                    .map(|c| Color::new(c[0], c[1], c[2]))
+                    .collect();
+                check(c.len(), "COLOR_0")?;
+                colors = Some(c);
+            }
+
+            let order: Vec<u32> = match prim.get("indices").and_then(Json::as_usize) {
+                Some(accessor) => self
+                    .read_typed(accessor, "indices", &["SCALAR"])?
+                    .0
+                    .into_iter()
+                    .map(|i| i as u32)
+                    .collect(),
+                None => (0..n_vertices as u32).collect(),
+            };
+            if order.iter().any(|&i| i as usize >= n_vertices) {
+                return Err(invalid(format!("mesh {} has an index out of range", index)));
+            }
+            let faces: Vec<[u32; 3]> = match mode {
+                MODE_TRIANGLES => order.chunks_exact(3).map(|f| [f[0], f[1], f[2]]).collect(),
+                MODE_TRIANGLE_STRIP => (2..order.len())
+                    .map(|i| {
+                        // keep the winding consistent on every other triangle
+                        if i % 2 == 0 {
+                            [order[i - 2], order[i - 1], order[i]]
+                        } else {
+                            [order[i - 1], order[i - 2], order[i]]
+                        }
+                    })
+                    .collect(),
+                MODE_TRIANGLE_FAN => (2..order.len())
+                    .map(|i| [order[0], order[i - 1], order[i]])
+                    .collect(),
+                _ => return Err(GltfError::Unsupported(format!("primitive mode {}", mode))),
+            };
+
+            let mat_ptr = match prim.get("material").and_then(Json::as_usize) {
+                Some(material) => self.material(material)?,
+                None => self.default_mat.clone(),
+            };
+            let mesh = TriangleMesh {
+                positions,
+                normals,
+                uvs,
+                colors,
+                faces,
+                mat_ptr,
+            };
+            self.triangles.extend(mesh.into_triangles());
+        }
+        Ok(())
+    }
+
+    fn material(&mut self, index: usize) -> Result<Arc<dyn Material>, GltfError> {
+        if let Some(mat) = self.materials.get(&index) {
+            return Ok(mat.clone());
+        }
+        let material = self.item("materials", index)?;
+        let extension = |name: &str, key: &str| {
+            material
+                .get("extensions")
+                .and_then(|e| e.get(name))
+                .and_then(|e| e.get(key))
+                .and_then(Json::as_f64)
+        };
+        let pbr = |key: &str| {
+            material
+                .get("pbrMetallicRoughness")
+                .and_then(|p| p.get(key))
+        };
+
+        let factor = pbr("baseColorFactor")
+            .and_then(Json::as_f64_vec)
+            .filter(|f| f.len() == 4)
+            .map_or(Color::ones(), |f| Color::new(f[0], f[1], f[2]));
+        let metallic = pbr("metallicFactor").and_then(Json::as_f64).unwrap_or(1.0);
+        let roughness = pbr("roughnessFactor").and_then(Json::as_f64).unwrap_or(1.0);
+        let emissive = material
+            .get("emissiveFactor")
+            .and_then(Json::as_f64_vec)
+            .filter(|f| f.len() == 3)
+            .map_or(Color::zero(), |f| Color::new(f[0], f[1], f[2]))
+            * extension("KHR_materials_emissive_strength", "emissiveStrength").unwrap_or(1.0);
+        let transmission =
+            extension("KHR_materials_transmission", "transmissionFactor").unwrap_or(0.0);
+
+        let mat: Arc<dyn Material> = if emissive.x.max(emissive.y).max(emissive.z) > 0.0 {
+            self.has_lights = true;
+            Arc::new(DiffuseLight::new_color(&emissive))
+        } else if transmission > 0.5 {
+            Arc::new(Dielectric::new(
+                extension("KHR_materials_ior", "ior").unwrap_or(1.5),
+            ))
+        } else if metallic >= 0.5 {
+            Arc::new(Metal::new(&factor, roughness * roughness))
+        } else {
+            let image = match pbr("baseColorTexture")
+                .and_then(|t| t.get("index"))
+                .and_then(Json::as_usize)
+            {
+                Some(texture) => self.texture_image(texture)?,
+                None => None,
+            };
+            Arc::new(Lambertian::new_arc(Arc::new(BaseColor { factor, image })))
+        };
+        self.materials.insert(index, mat.clone());
+        Ok(mat)
+    }
+
+    /// Image behind texture `index`, `None` if it only has a source in an
+    /// unsupported extension.
+    fn texture_image(&mut self, index: usize) -> Result<Option<Arc<ImageTexture>>, GltfError> {
+        let source = match self
+            .item("textures", index)?
+            .get("source")
+            .and_then(Json::as_usize)
+        {
+            Some(source) => source,
+            None => return Ok(None),
+        };
+        if let Some(image) = self.images.get(&source) {
+            return Ok(Some(image.clone()));
+        }
+
+        let image = self.item("images", source)?;
+        let data = match (
+            image.get("uri").and_then(Json::as_str),
+            image.get("bufferView").and_then(Json::as_usize),
+        ) {
+            (Some(uri), _) => self.read_uri(uri)?,
+            (None, Some(view)) => self.buffer_view(view)?.to_vec(),
+            _ => return Err(invalid(format!("image {} has no data", source))),
+        };
+        let decoded = image::load_from_memory(&data).map_err(|e| GltfError::Image(source, e))?;
+        let texture = Arc::new(ImageTexture::from_image(decoded));
+        self.images.insert(source, texture.clone());
+        Ok(Some(texture))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut res = vec![];
+        for word in &[0x4654_6C67, 2, total as u32, json.len() as u32, GLB_JSON] {
+            res.extend_from_slice(&u32::to_le_bytes(*word));
+        }
+        res.extend(json);
+        res.extend_from_slice(&u32::to_le_bytes(bin.len() as u32));
+        res.extend_from_slice(&u32::to_le_bytes(GLB_BIN));
+        res.extend_from_slice(bin);
+        res
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(decode_base64("SGVsbG8=").unwrap(), b"Hello");
+        assert_eq!(decode_base64("SGVs\nbG8h").unwrap(), b"Hello!");
+        assert!(decode_base64("SGV*").is_none());
+        assert_eq!(percent_decode("my%20mesh.bin"), "my mesh.bin");
+    }
+
+    #[test]
+    fn test_glb_hierarchy() {
+        let mut bin = vec![];
+        for x in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&x.to_le_bytes());
+        }
+        for i in &[0u16, 1, 2, 0] {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "scene": 0,
+            "scenes": [{"nodes": [0, 1]}],
+            "nodes": [
+                {"mesh": 0, "translation": [0, 0, -5], "children": [2]},
+                {"camera": 0, "rotation": [0, 1, 0, 0], "translation": [0, 0, 1]},
+                {"mesh": 0, "translation": [0, 0, 2], "scale": [2, 2, 2]}
+            ],
+            "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0}}],
+            "buffers": [{"byteLength": 44}],
+            "bufferViews": [
+                {"buffer": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ]
+        }"#;
+        let scene = parse_gltf(&glb(json, &bin), Path::new(""), 1.0).unwrap();
+
+        // the camera is turned around to look down +z
+        assert_eq!(scene.cameras.len(), 1);
+        let cam = &scene.cameras[0];
+        assert!((cam.origin - Point3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((cam.w - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+
+        // parent triangle at z = -5, scaled child at z = -3
+        let up = Ray::new(Point3::new(0.2, 0.2, -10.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = scene.world.hit(&up, 0.001, 100.0).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-6);
+        let (attenuation, _) = rec.mat_ptr.scatter(&up, &rec).unwrap();
+        assert_eq!(attenuation, Color::new(1.0, 0.0, 0.0));
+
+        let wide = Ray::new(Point3::new(1.5, 0.2, -10.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = scene.world.hit(&wide, 0.001, 100.0).unwrap();
+        assert!((rec.t - 7.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_bad_accessors() {
+        let mut bin = vec![];
+        for x in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&x.to_le_bytes());
+        }
+        // all-zero normals
+        bin.extend_from_slice(&[0; 36]);
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "nodes": [{"mesh": 0}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1}}]}],
+            "buffers": [{"byteLength": 72}],
+            "bufferViews": [
+                {"buffer": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 36}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"}
+            ]
+        }"#;
+        let scene = parse_gltf(&glb(json, &bin), Path::new(""), 1.0).unwrap();
+        let ray = Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = scene.world.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        // offsets so large that the range check itself would overflow
+        let huge = json.replace(
+            r#""count": 3, "type": "VEC3"}
+            ]"#,
+            r#""count": 3, "type": "VEC3", "byteOffset": 1e300}
+            ]"#,
+        );
+        assert!(matches!(
+            parse_gltf(&glb(&huge, &bin), Path::new(""), 1.0),
+            Err(GltfError::Invalid(_))
+        ));
+
+        // a stride that would let elements overlap
+        let strided = json.replace(
+            r#""byteLength": 36},"#,
+            r#""byteLength": 36, "byteStride": 4},"#,
+        );
+        assert_ne!(strided, json);
+        assert!(matches!(
+            parse_gltf(&glb(&strided, &bin), Path::new(""), 1.0),
+            Err(GltfError::Invalid(_))
+        ));
+
+        // zeros without a buffer view, far more than the file holds
+        let zeros = json.replace(
+            r#"{"bufferView": 1, "componentType": 5126, "count": 3,"#,
+            r#"{"componentType": 5126, "count": 1000000000000,"#,
+        );
+        assert_ne!(zeros, json);
+        assert!(matches!(
+            parse_gltf(&glb(&zeros, &bin), Path::new(""), 1.0),
+            Err(GltfError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_accessor_types() {
+        let mut bin = vec![];
+        for x in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            bin.extend_from_slice(&x.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "nodes": [{"mesh": 0}],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "ATTR": 1}}]}],
+            "buffers": [{"byteLength": 36}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "TYPE"}
+            ]
+        }"#;
+        let load = |attr: &str, ty: &str| {
+            let json = json.replace("ATTR", attr).replace("TYPE", ty);
+            parse_gltf(&glb(&json, &bin), Path::new(""), 1.0)
+        };
+        assert!(load("COLOR_0", "VEC3").is_ok());
+        for &(attr, ty) in &[
+            ("COLOR_0", "SCALAR"),
+            ("COLOR_0", "VEC2"),
+            ("NORMAL", "SCALAR"),
+            ("TEXCOORD_0", "VEC3"),
+        ] {
+            assert!(
+                matches!(load(attr, ty), Err(GltfError::Invalid(_))),
+                "{} as {}",
+                attr,
+                ty
+            );
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::triangle::*;
+use std::path::Path;
+
+/// Terrain over a regular grid of height samples. The samples span `size.x`
+/// by `size.z` from `corner`, rising up to `size.y` above it, and every grid
+/// cell is split into two triangles with normals interpolated from the
+/// neighbouring samples. Rays walk the grid cell by cell, so the cost grows
+/// with the distance travelled over the terrain rather than with its area.
+pub struct Heightfield {
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    nx: usize,
+    nz: usize,
+    corner: Point3,
+    size: Vec3,
+    mp: Arc<dyn Material>,
+    bbox: AABB,
+}
+
+impl Heightfield {
+    /// `heights` holds `nx * nz` samples in [0, 1], row by row along +z.
+    pub fn new(
+        heights: Vec<f64>,
+        nx: usize,
+        nz: usize,
+        corner: Point3,
+        size: Vec3,
+        mp: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            nx >= 2 && nz >= 2,
+            "a heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), nx * nz);
+
+        let (lo, hi) = heights
+            .iter()
+            .fold((std::f64::INFINITY, -std::f64::INFINITY), |(lo, hi), &h| {
+                (lo.min(h), hi.max(h))
+            });
+        let bbox = AABB::new(
+            Point3::new(corner.x, corner.y + lo * size.y - 0.0001, corner.z),
+            Point3::new(
+                corner.x + size.x,
+                corner.y + hi * size.y + 0.0001,
+                corner.z + size.z,
+            ),
+        );
+
+        let mut res = Self {
+            heights,
+            normals: vec![],
+            nx,
+            nz,
+            corner,
+            size,
+            mp,
+            bbox,
+        };
+        res.normals = (0..nz)
+            .flat_map(|j| (0..nx).map(move |i| (i, j)))
+            .map(|(i, j)| res.sample_normal(i, j))
+            .collect();
+        res
+    }
+
+    /// Heightfield with one sample per pixel of a grayscale image, whose
+    /// rows run along +z. 16-bit images keep their full precision. Images
+    /// narrower than 2 pixels either way give a `DimensionMismatch` error.
+    pub fn from_image<P: AsRef<Path>>(
+        path: P,
+        corner: Point3,
+        size: Vec3,
+        mp: Arc<dyn Material>,
+    ) -> Result<Self, image::ImageError> {
+        let (nx, nz, heights) = match image::open(path)? {
+            image::DynamicImage::ImageLuma16(img) => (
+                img.width(),
+                img.height(),
+                img.pixels().map(|p| p[0] as f64 / 65535.0).collect(),
+            ),
+            other => {
+                let img = other.to_luma();
+                (
+                    img.width(),
+                    img.height(),
+                    img.pixels().map(|p| p[0] as f64 / 255.0).collect(),
+                )
+            }
+        };
+        if nx < 2 || nz < 2 {
+            return Err(image::ImageError::Parameter(
+                image::error::ParameterError::from_kind(
+                    image::error::ParameterErrorKind::DimensionMismatch,
+                ),
+            ));
+        }
+        Ok(Self::new(
+            heights,
+            nx as usize,
+            nz as usize,
+            corner,
+            size,
+            mp,
+        ))
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            self.size.x / (self.nx - 1) as f64,
+            self.size.z / (self.nz - 1) as f64,
+        )
+    }
+
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.nx + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        Point3::new(
+            self.corner.x + i as f64 * dx,
+            self.corner.y + self.height(i, j) * self.size.y,
+            self.corner.z + j as f64 * dz,
+        )
+    }
+
+    /// Normal at a sample from central differences (one-sided at the edges).
+    fn sample_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (dx, dz) = self.cell_size();
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.nx - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.nz - 1));
+        let slope_x =
+            (self.height(i1, j) - self.height(i0, j)) * self.size.y / ((i1 - i0) as f64 * dx);
+        let slope_z =
+            (self.height(i, j1) - self.height(i, j0)) * self.size.y / ((j1 - j0) as f64 * dz);
+        Vec3::new(-slope_x, 1.0, -slope_z).unit()
+    }
+
+    fn hit_cell(&self, i: usize, j: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let corners = [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)];
+        let mut closest = None as Option<HitRecord>;
+        // wound so that the faces point up
+        for tri in &[[0, 2, 1], [1, 2, 3]] {
+            let idx = [corners[tri[0]], corners[tri[1]], corners[tri[2]]];
+            let vertices = [
+                self.vertex(idx[0].0, idx[0].1),
+                self.vertex(idx[1].0, idx[1].1),
+                self.vertex(idx[2].0, idx[2].1),
+            ];
+            let t_max = closest.as_ref().map_or(t_max, |rec| rec.t);
+            if let Some(res) = intersect(&vertices, ray, t_min, t_max) {
+                let normal = |(a, b): (usize, usize)| self.normals[b * self.nx + a];
+                // UVs map back onto the source image, whose first row is at the top
+                let uv = |(a, b): (usize, usize)| {
+                    (
+                        a as f64 / (self.nx - 1) as f64,
+                        1.0 - b as f64 / (self.nz - 1) as f64,
+                    )
+                };
+                closest = Some(shade(
+                    &vertices,
+                    Some([normal(idx[0]), normal(idx[1]), normal(idx[2])]),
+                    Some([uv(idx[0]), uv(idx[1]), uv(idx[2])]),
+                    None,
+                    &self.mp,
+                    ray,
+                    res,
+                ));
+            }
+        }
+        closest
+    }
+
+    /// Lowest and highest sample of a cell.
+    fn cell_range(&self, i: usize, j: usize) -> (f64, f64) {
+        let h = [
+            self.height(i, j),
+            self.height(i + 1, j),
+            self.height(i, j + 1),
+            self.height(i + 1, j + 1),
+        ];
+        let lo = h[0].min(h[1]).min(h[2]).min(h[3]);
+        let hi = h[0].max(h[1]).max(h[2]).max(h[3]);
+        (
+            self.corner.y + lo * self.size.y,
+            self.corner.y + hi * self.size.y,
+        )
+    }
+}
+
+impl Object for Heightfield {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = self.bbox.hit_range(ray, t_min, t_max)?;
+
+        // walk the cells under the ray in grid coordinates (Amanatides & Woo)
+        let (dx, dz) = self.cell_size();
+        let (cells_x, cells_z) = (self.nx - 1, self.nz - 1);
+        let entry = ray.at(t_enter);
+        let gx = (entry.x - self.corner.x) / dx;
+        let gz = (entry.z - self.corner.z) / dz;
+        let mut i = (gx.floor().max(0.0) as usize).min(cells_x - 1);
+        let mut j = (gz.floor().max(0.0) as usize).min(cells_z - 1);
+
+        let axis_setup = |g: f64, cell: usize, speed: f64| {
+            if speed > 0.0 {
+                (1, t_enter + ((cell + 1) as f64 - g) / speed, 1.0 / speed)
+            } else if speed < 0.0 {
+                (-1, t_enter + (cell as f64 - g) / speed, -1.0 / speed)
+            } else {
+                (0, std::f64::INFINITY, std::f64::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) = axis_setup(gx, i, ray.dir.x / dx);
+        let (step_z, mut next_z, delta_z) = axis_setup(gz, j, ray.dir.z / dz);
+
+        let mut t_cell = t_enter;
+        loop {
+            let t_leave = next_x.min(next_z).min(t_exit);
+
+            // skip cells the ray passes entirely above or below
+            let (lo, hi) = self.cell_range(i, j);
+            let (y0, y1) = (ray.at(t_cell).y, ray.at(t_leave).y);
+            if y0.max(y1) >= lo - 0.0001 && y0.min(y1) <= hi + 0.0001 {
+                if let Some(rec) = self.hit_cell(i, j, ray, t_min, t_max) {
+                    return Some(rec);
+                }
+            }
+
+            if t_leave >= t_exit {
+                return None;
+            }
+            t_cell = t_leave;
+            if next_x < next_z {
+                if (step_x < 0 && i == 0) || (step_x > 0 && i + 1 == cells_x) {
+                    return None;
+                }
+                i = (i as isize + step_x) as usize;
+                next_x += delta_x;
+            } else {
+                if (step_z < 0 && j == 0) || (step_z > 0 && j + 1 == cells_z) {
+                    return None;
+                }
+                j = (j as isize + step_z) as usize;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp() -> Heightfield {
+        // rises along x from 0 to 1 over [0, 4] x [0, 4]
+        let heights = (0..5)
+            .flat_map(|_| (0..5).map(|i| i as f64 / 4.0))
+            .collect();
+        Heightfield::new(
+            heights,
+            5,
+            5,
+            Point3::zero(),
+            Vec3::new(4.0, 1.0, 4.0),
+            Arc::new(Lambertian::new(&Color::ones())),
+        )
+    }
+
+    #[test]
+    fn test_hit_from_above() {
+        let field = ramp();
+        let ray = Ray::new(Point3::new(2.5, 10.0, 1.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = field.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.p.y - 0.625).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-0.25, 1.0, 0.0).unit()).length() < 1e-9);
+        assert!((rec.u - 0.625).abs() < 1e-9);
+        assert!((rec.v - 0.625).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_grazing_ray() {
+        let field = ramp();
+        // level ray at y = 0.5 runs into the slope at x = 2
+        let ray = Ray::new(Point3::new(-1.0, 0.5, 3.3), Vec3::new(1.0, 0.0, 0.1), 0.0);
+        let rec = field.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.p.x - 2.0).abs() < 1e-9);
+
+        // and passes over it just above the top
+        let ray = Ray::new(Point3::new(-1.0, 1.01, 3.3), Vec3::new(1.0, 0.0, 0.1), 0.0);
+        assert!(field.hit(&ray, 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_image_too_small() {
+        let path = std::env::temp_dir().join("heightfield_1x5.png");
+        image::GrayImage::new(1, 5).save(&path).unwrap();
+        let res = Heightfield::from_image(
+            &path,
+            Point3::zero(),
+            Vec3::ones(),
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(image::ImageError::Parameter(_))));
+    }
+}
@@ -0,0 +1,155 @@
+pub use crate::object::*;
+use crate::transform::*;
+
+/// One placement of shared geometry. Any number of instances can point at
+/// the same object (typically a `BvhNode` over a mesh) while each carries
+/// only its own transform and, optionally, a material replacing the one the
+/// geometry was built with.
+///
+/// Only the inverse of the placement is kept: affine maps preserve the ray
+/// parameter, so hit points come from the world ray and normals from the
+/// inverse transposed on the fly. An instance whose matrix can't be
+/// inverted, such as one scaled to zero to hide it, is never hit.
+#[derive(Clone)]
+pub struct Instance {
+    geometry: Arc<dyn Object>,
+    inverse: Option<Mat4>,
+    mat_ptr: Option<Arc<dyn Material>>,
+    bbox: Option<AABB>,
+}
+
+impl Instance {
+    /// `matrix` maps the geometry's object space to world space.
+    pub fn new(geometry: Arc<dyn Object>, matrix: Mat4) -> Self {
+        let bbox = geometry
+            .bounding_box(0.0, 1.0)
+            .map(|b| matrix.transform_box(&b));
+        Self {
+            geometry,
+            inverse: matrix.inverse(),
+            mat_ptr: None,
+            bbox,
+        }
+    }
+
+    pub fn new_with_material(
+        geometry: Arc<dyn Object>,
+        matrix: Mat4,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            mat_ptr: Some(mat_ptr),
+            ..Self::new(geometry, matrix)
+        }
+    }
+
+    pub fn geometry(&self) -> &Arc<dyn Object> {
+        &self.geometry
+    }
+
+    /// Whether the matrix was singular, leaving nothing to hit.
+    pub fn is_hidden(&self) -> bool {
+        self.inverse.is_none()
+    }
+
+    /// Moves the instance to a new placement, keeping geometry and material.
+    pub fn set_matrix(&mut self, matrix: Mat4) {
+        *self = Self {
+            mat_ptr: self.mat_ptr.take(),
+            ..Self::new(self.geometry.clone(), matrix)
+        };
+    }
+}
+
+impl Instance {
+    fn local_ray(&self, ray: &Ray) -> Option<Ray> {
+        let inverse = self.inverse.as_ref()?;
+        Some(Ray::new(
+            inverse.transform_point(ray.orig),
+            inverse.transform_vector(ray.dir),
+            ray.tm,
+        ))
+    }
+}
+
+impl Object for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let inverse = self.inverse.as_ref()?;
+        let mut rec = self.geometry.hit(&self.local_ray(ray)?, t_min, t_max)?;
+        rec.p = ray.at(rec.t);
+        rec.normal = inverse.transform_normal(rec.normal).unit();
+        if let Some(mat) = &self.mat_ptr {
+            rec.mat_ptr = mat.clone();
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.bbox
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        match self.local_ray(ray) {
+            Some(local) => self.geometry.occluded(&local, t_min, t_max),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_geometry() {
+        let sphere: Arc<dyn Object> = Arc::new(Sphere::new(
+            Point3::zero(),
+            1.0,
+            Arc::new(Lambertian::new(&Color::ones())),
+        ));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::new(1.0, 0.0, 0.0)));
+        let plain = Instance::new(sphere.clone(), Mat4::translation(Vec3::new(0.0, 0.0, -5.0)));
+        let painted = Instance::new_with_material(
+            sphere.clone(),
+            Mat4::translation(Vec3::new(3.0, 0.0, -5.0)) * Mat4::scaling(Vec3::ones() * 0.5),
+            red,
+        );
+        assert_eq!(Arc::strong_count(&sphere), 3);
+
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = plain.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!(painted.hit(&ray, 0.001, 100.0).is_none());
+
+        let ray = Ray::new(Point3::new(3.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = painted.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        let (attenuation, _) = rec.mat_ptr.scatter(&ray, &rec).unwrap();
+        assert_eq!(attenuation, Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_zero_scale() {
+        let sphere: Arc<dyn Object> = Arc::new(Sphere::new(
+            Point3::zero(),
+            1.0,
+            Arc::new(Lambertian::new(&Color::ones())),
+        ));
+        let mut inst = Instance::new(sphere, Mat4::scaling(Vec3::zero()));
+        assert!(inst.is_hidden());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(inst.hit(&ray, 0.001, 100.0).is_none());
+        assert!(!inst.occluded(&ray, 0.001, 100.0));
+
+        // and it comes back when scaled up again
+        inst.set_matrix(Mat4::identity());
+        assert!(!inst.is_hidden());
+        let rec = inst.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,418 @@
+use crate::bvh::*;
+
+/// Node of a `LinearBvh`. Leaves hold `count` primitives from `offset`;
+/// inner nodes (`count == 0`) have their first child right after them and
+/// the second at `offset`, split along `axis`.
+struct LinearNode {
+    bbox: AABB,
+    offset: u32,
+    count: u32,
+    axis: u8,
+}
+
+/// Objects to build over, each with its bounding box and its position in
+/// the list the tree was created from.
+type IndexedObjects = Vec<(AABB, (usize, Arc<dyn Object>))>;
+
+/// Refitting gives up and rebuilds once the tree's SAH cost has grown by
+/// this factor over the cost right after the last build.
+const REBUILD_COST_RATIO: f64 = 1.5;
+
+/// BVH flattened into one array of nodes in depth-first order, with the
+/// primitives kept in a second array in leaf order. Traversal runs on an
+/// explicit stack, visits the child on the near side of the split first
+/// and narrows `t_max` with every hit, so farther subtrees are culled by
+/// their boxes. A drop-in replacement for `BvhNode`.
+///
+/// For animation, `refit` takes the moved objects and updates the boxes in
+/// place instead of building a new tree every frame.
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<Arc<dyn Object>>,
+    unbounded: Vec<Arc<dyn Object>>,
+    /// Position in the original list of each primitive and unbounded object.
+    order: Vec<usize>,
+    unbounded_order: Vec<usize>,
+    if_dark: bool,
+    method: SplitMethod,
+    build_cost: f64,
+}
+
+impl LinearBvh {
+    pub fn new_boxed(list: HittableList, time0: f64, time1: f64) -> Arc<dyn Object> {
+        Arc::new(Self::new(
+            list.objects,
+            time0,
+            time1,
+            list.if_dark,
+            SplitMethod::Sah,
+        ))
+    }
+
+    /// Objects without a bounding box are tested after the tree, one by one.
+    pub fn new(
+        objects: Vec<Arc<dyn Object>>,
+        time0: f64,
+        time1: f64,
+        if_dark: bool,
+        method: SplitMethod,
+    ) -> Self {
+        let mut bounded = vec![];
+        let mut res = Self {
+            nodes: vec![],
+            primitives: vec![],
+            unbounded: vec![],
+            order: vec![],
+            unbounded_order: vec![],
+            if_dark,
+            method,
+            build_cost: 0.0,
+        };
+        for (i, ob) in objects.into_iter().enumerate() {
+            match ob.bounding_box(time0, time1) {
+                Some(bbox) => bounded.push((bbox, (i, ob))),
+                None => {
+                    res.unbounded.push(ob);
+                    res.unbounded_order.push(i);
+                }
+            }
+        }
+        if !bounded.is_empty() {
+            res.nodes.reserve(2 * bounded.len() - 1);
+            res.primitives.reserve(bounded.len());
+            res.order.reserve(bounded.len());
+            res.build(bounded);
+        }
+        res.build_cost = res.cost();
+        res
+    }
+
+    /// Appends the subtree over `objects` and returns its bounding box.
+    fn build(&mut self, mut objects: IndexedObjects) -> AABB {
+        let index = self.nodes.len();
+        if objects.len() == 1 {
+            let (bbox, (i, ob)) = objects.remove(0);
+            self.nodes.push(LinearNode {
+                bbox,
+                offset: self.primitives.len() as u32,
+                count: 1,
+                axis: 0,
+            });
+            self.primitives.push(ob);
+            self.order.push(i);
+            return bbox;
+        }
+
+        let (right_objects, axis) = split_objects(&mut objects, self.method);
+        self.nodes.push(LinearNode {
+            bbox: objects[0].0,
+            offset: 0,
+            count: 0,
+            axis: axis as u8,
+        });
+        let left_box = self.build(objects);
+        self.nodes[index].offset = self.nodes.len() as u32;
+        let right_box = self.build(right_objects);
+        let bbox = surrounding_box(left_box, right_box);
+        self.nodes[index].bbox = bbox;
+        bbox
+    }
+
+    /// SAH estimate of the work per ray that hits the root box, in units of
+    /// one object intersection (see `BvhStats::cost`).
+    pub fn cost(&self) -> f64 {
+        let root_area = match self.nodes.first() {
+            Some(root) => root.bbox.surface_area(),
+            None => return 0.0,
+        };
+        if root_area <= 0.0 {
+            return self.primitives.len() as f64;
+        }
+        let weighted: f64 = self
+            .nodes
+            .iter()
+            .map(|node| {
+                if node.count > 0 {
+                    node.bbox.surface_area() * node.count as f64
+                } else {
+                    node.bbox.surface_area() * TRAVERSAL_COST
+                }
+            })
+            .sum();
+        weighted / root_area
+    }
+
+    /// How much the tree has degraded through refitting: its cost relative
+    /// to the cost right after it was built. Starts at 1.
+    pub fn quality(&self) -> f64 {
+        if self.build_cost > 0.0 {
+            self.cost() / self.build_cost
+        } else {
+            1.0
+        }
+    }
+
+    /// Swaps in `objects`, the same objects as at construction and in the
+    /// same order but moved, and updates the node boxes bottom-up while
+    /// keeping the tree's shape. Once the shape fits the new positions so
+    /// badly that `quality` exceeds `REBUILD_COST_RATIO`, or an object lost
+    /// its bounding box, the tree is rebuilt instead. Returns whether it was.
+    pub fn refit(&mut self, objects: Vec<Arc<dyn Object>>, time0: f64, time1: f64) -> bool {
+        assert_eq!(
+            objects.len(),
+            self.primitives.len() + self.unbounded.len(),
+            "refit needs the objects the tree was built over"
+        );
+        let mut objects: Vec<Option<Arc<dyn Object>>> = objects.into_iter().map(Some).collect();
+        for (ob, &i) in self.primitives.iter_mut().zip(&self.order) {
+            *ob = objects[i].take().unwrap();
+        }
+        for (ob, &i) in self.unbounded.iter_mut().zip(&self.unbounded_order) {
+            *ob = objects[i].take().unwrap();
+        }
+
+        // children always come after their parent, so going backwards
+        // updates them first
+        for index in (0..self.nodes.len()).rev() {
+            let offset = self.nodes[index].offset as usize;
+            let count = self.nodes[index].count as usize;
+            let bbox = if count > 0 {
+                let boxes: Option<Vec<AABB>> = self.primitives[offset..offset + count]
+                    .iter()
+                    .map(|ob| ob.bounding_box(time0, time1))
+                    .collect();
+                match boxes {
+                    Some(boxes) => boxes[1..]
+                        .iter()
+                        .fold(boxes[0], |acc, b| surrounding_box(acc, *b)),
+                    None => {
+                        self.rebuild(time0, time1);
+                        return true;
+                    }
+                }
+            } else {
+                surrounding_box(self.nodes[index + 1].bbox, self.nodes[offset].bbox)
+            };
+            self.nodes[index].bbox = bbox;
+        }
+
+        if self.quality() > REBUILD_COST_RATIO {
+            self.rebuild(time0, time1);
+            return true;
+        }
+        false
+    }
+
+    fn rebuild(&mut self, time0: f64, time1: f64) {
+        let mut objects = vec![None; self.order.len() + self.unbounded_order.len()];
+        for (ob, &i) in self.primitives.iter().zip(&self.order) {
+            objects[i] = Some(ob.clone());
+        }
+        for (ob, &i) in self.unbounded.iter().zip(&self.unbounded_order) {
+            objects[i] = Some(ob.clone());
+        }
+        let objects = objects.into_iter().map(Option::unwrap).collect();
+        *self = Self::new(objects, time0, time1, self.if_dark, self.method);
+    }
+}
+
+impl Object for LinearBvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest = None as Option<HitRecord>;
+        let mut t_max = t_max;
+
+        if !self.nodes.is_empty() {
+            let dir_is_neg = [ray.dir.x < 0.0, ray.dir.y < 0.0, ray.dir.z < 0.0];
+            let mut stack = Vec::with_capacity(64);
+            let mut current = 0;
+            loop {
+                let node = &self.nodes[current];
+                if node.bbox.hit(ray, t_min, t_max) {
+                    let offset = node.offset as usize;
+                    if node.count > 0 {
+                        for ob in &self.primitives[offset..offset + node.count as usize] {
+                            if let Some(rec) = ob.hit(ray, t_min, t_max) {
+                                t_max = rec.t;
+                                closest = Some(rec);
+                            }
+                        }
+                    } else if dir_is_neg[node.axis as usize] {
+                        // the second child lies on the far side of the split
+                        stack.push(current + 1);
+                        current = offset;
+                        continue;
+                    } else {
+                        stack.push(offset);
+                        current += 1;
+                        continue;
+                    }
+                }
+                match stack.pop() {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+        }
+
+        for ob in &self.unbounded {
+            if let Some(rec) = ob.hit(ray, t_min, t_max) {
+                t_max = rec.t;
+                closest = Some(rec);
+            }
+        }
+        closest
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        if self
+            .unbounded
+            .iter()
+            .any(|ob| ob.occluded(ray, t_min, t_max))
+        {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        // any hit will do, so the order of the children doesn't matter
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let node = &self.nodes[current];
+            if !node.bbox.hit(ray, t_min, t_max) {
+                continue;
+            }
+            let offset = node.offset as usize;
+            if node.count > 0 {
+                let leaf = &self.primitives[offset..offset + node.count as usize];
+                if leaf.iter().any(|ob| ob.occluded(ray, t_min, t_max)) {
+                    return true;
+                }
+            } else {
+                stack.push(offset);
+                stack.push(current + 1);
+            }
+        }
+        false
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.nodes.first().map(|node| node.bbox)
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        if self.if_dark {
+            Color::zero()
+        } else {
+            Color::ones() * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quadric::Plane;
+
+    fn scene() -> Vec<Arc<dyn Object>> {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::ones()));
+        let mut rng = rand::thread_rng();
+        let mut objects: Vec<Arc<dyn Object>> = (0..200)
+            .map(|_| {
+                let center = Point3::new(
+                    rng.gen_range(-5.0, 5.0),
+                    rng.gen_range(-5.0, 5.0),
+                    rng.gen_range(-5.0, 5.0),
+                );
+                Arc::new(Sphere::new(center, rng.gen_range(0.1, 0.6), mat.clone()))
+                    as Arc<dyn Object>
+            })
+            .collect();
+        objects.push(Arc::new(Plane::new(
+            Point3::new(0.0, -6.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            mat,
+        )));
+        objects
+    }
+
+    #[test]
+    fn test_matches_list() {
+        let objects = scene();
+        let mut list = HittableList::new(true);
+        for ob in &objects {
+            list.push(ob.clone());
+        }
+        let mut rng = rand::thread_rng();
+        for &method in &[SplitMethod::Median, SplitMethod::Sah] {
+            let bvh = LinearBvh::new(objects.clone(), 0.0, 1.0, true, method);
+            assert_eq!(bvh.nodes.len(), 2 * 200 - 1);
+            assert!(bvh.bounding_box(0.0, 1.0).is_none());
+            for _ in 0..500 {
+                let orig = Point3::new(
+                    rng.gen_range(-8.0, 8.0),
+                    rng.gen_range(-8.0, 8.0),
+                    rng.gen_range(-8.0, 8.0),
+                );
+                let dir = Vec3::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                );
+                let ray = Ray::new(orig, dir, 0.0);
+                let expected = list.hit(&ray, 0.001, 100.0).map(|rec| rec.t);
+                assert_eq!(bvh.hit(&ray, 0.001, 100.0).map(|rec| rec.t), expected);
+                assert_eq!(
+                    bvh.occluded(&ray, 0.001, 2.0),
+                    list.occluded(&ray, 0.001, 2.0)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_refit() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::ones()));
+        let row = |place: &dyn Fn(usize) -> Point3| -> Vec<Arc<dyn Object>> {
+            (0..100)
+                .map(|i| Arc::new(Sphere::new(place(i), 0.3, mat.clone())) as Arc<dyn Object>)
+                .collect()
+        };
+        let mut bvh = LinearBvh::new(
+            row(&|i| Point3::new(i as f64, 0.0, 0.0)),
+            0.0,
+            1.0,
+            true,
+            SplitMethod::Sah,
+        );
+        assert!((bvh.quality() - 1.0).abs() < 1e-9);
+
+        // everything drifts a little: the old tree still fits
+        let moved = row(&|i| Point3::new(i as f64 + 0.2, (i % 3) as f64 * 0.1, 0.0));
+        assert!(!bvh.refit(moved.clone(), 0.0, 1.0));
+        assert!(bvh.quality() < 1.5);
+        let mut list = HittableList::new(true);
+        for ob in moved {
+            list.push(ob);
+        }
+        for i in 0..100 {
+            let ray = Ray::new(
+                Point3::new(i as f64 + 0.4, 0.1, 5.0),
+                Vec3::new(0.0, 0.0, -1.0),
+                0.0,
+            );
+            let expected = list.hit(&ray, 0.001, 100.0).map(|rec| rec.t);
+            assert_eq!(bvh.hit(&ray, 0.001, 100.0).map(|rec| rec.t), expected);
+        }
+
+        // the spheres trade places: neighbours in the tree end up far apart
+        let shuffled = row(&|i| Point3::new(((i * 37) % 100) as f64, 0.0, 0.0));
+        assert!(bvh.refit(shuffled, 0.0, 1.0));
+        assert!((bvh.quality() - 1.0).abs() < 1e-9);
+        let ray = Ray::new(Point3::new(37.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(bvh.hit(&ray, 0.001, 100.0).is_some());
+    }
+}
@@ -2,10 +2,31 @@ mod aabb;
 mod aarect;
 mod bvh;
 mod camera;
+mod csg;
+mod curve;
+mod gltf;
+mod heightfield;
+mod instance;
+mod json;
+mod linear_bvh;
+mod medium;
+mod metaballs;
+mod obj;
 mod object;
+mod ply;
+mod pointcloud;
+mod quad;
+mod quadric;
 mod ray;
+mod sdf;
 mod sence;
+mod subdivision;
+mod text3d;
 mod texture;
+mod tlas;
+mod torus;
+mod transform;
+mod triangle;
 #[allow(clippy::float_cmp)]
 mod vec3;
 use image::{ImageBuffer, Rgb, RgbImage};
@@ -15,13 +36,33 @@ pub use std::{sync, vec};
 
 pub use aarect::*;
 pub use camera::Camera;
+pub use csg::*;
+pub use curve::*;
+pub use gltf::*;
+pub use heightfield::*;
+pub use instance::*;
+pub use linear_bvh::*;
+pub use medium::*;
+pub use metaballs::*;
+pub use obj::*;
 pub use object::*;
+pub use ply::*;
+pub use pointcloud::*;
+pub use quad::*;
+pub use quadric::*;
 pub use ray::Ray;
 use rusttype::Font;
+pub use sdf::*;
 pub use sence::*;
 use std::sync::mpsc::channel;
+pub use subdivision::*;
+pub use text3d::*;
 pub use texture::*;
 use threadpool::ThreadPool;
+pub use tlas::*;
+pub use torus::*;
+pub use transform::*;
+pub use triangle::*;
 pub use vec3::{Color, Point3, Vec3};
 
 const AUTHOR: &str = "JolyneFr";
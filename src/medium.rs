@@ -0,0 +1,75 @@
+pub use crate::object::*;
+use rand::Rng;
+
+/// Volume of constant density filling a closed `boundary` object. A ray
+/// travelling through it scatters after an exponentially distributed
+/// distance, at which point the `Isotropic` phase function takes over.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Object>,
+    phase_function: Arc<dyn Material>,
+    neg_inv_density: f64,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Object>, density: f64, a: Arc<dyn Texture>) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Isotropic::new(a)),
+            neg_inv_density: -1.0 / density,
+        }
+    }
+
+    pub fn new_color(boundary: Arc<dyn Object>, density: f64, c: &Color) -> Self {
+        Self {
+            boundary,
+            phase_function: Arc::new(Isotropic::new_color(c)),
+            neg_inv_density: -1.0 / density,
+        }
+    }
+}
+
+impl Object for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // find where the ray enters and leaves the boundary, even if the
+        // origin is already inside it
+        let rec1 = self
+            .boundary
+            .hit(ray, -std::f64::INFINITY, std::f64::INFINITY)?;
+        let rec2 = self
+            .boundary
+            .hit(ray, rec1.t + 0.0001, std::f64::INFINITY)?;
+
+        let t_enter = rec1.t.max(t_min).max(0.0);
+        let t_exit = rec2.t.min(t_max);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.dir.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let r: f64 = rand::thread_rng().gen();
+        let hit_distance = self.neg_inv_density * r.ln();
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let tin = t_enter + hit_distance / ray_length;
+        // normal and front_face are arbitrary inside a volume
+        let mut rec = HitRecord::new(
+            ray.at(tin),
+            Vec3::new(1.0, 0.0, 0.0),
+            tin,
+            self.phase_function.clone(),
+        );
+        rec.front_face = true;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
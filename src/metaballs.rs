@@ -0,0 +1,321 @@
+pub use crate::object::*;
+use crate::torus::solve_quadratic;
+
+/// Point charge of a `Metaballs` field. Its contribution falls off as
+/// `strength * (1 - r^2 / radius^2)^3` and vanishes beyond `radius`, so it
+/// only has to be considered where a ray crosses its sphere of influence.
+/// A negative `strength` carves into the surrounding blobs.
+#[derive(Copy, Clone, Debug)]
+pub struct Charge {
+    pub center: Point3,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+impl Charge {
+    pub fn new(center: Point3, radius: f64, strength: f64) -> Self {
+        assert!(radius > 0.0, "a charge needs a positive radius");
+        Self {
+            center,
+            radius,
+            strength,
+        }
+    }
+}
+
+fn poly_eval(p: &[f64], x: f64) -> f64 {
+    p.iter().rev().fold(0.0, |acc, &c| acc * x + c)
+}
+
+fn poly_mul(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut res = vec![0.0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            res[i + j] += x * y;
+        }
+    }
+    res
+}
+
+/// Roots of the polynomial `p` (lowest coefficient first) inside [a, b],
+/// ascending. The roots of the derivative split the interval into pieces on
+/// which `p` is monotonic, and each piece with a sign change is bisected, so
+/// no crossing can be stepped over.
+fn poly_roots_in(p: &[f64], a: f64, b: f64) -> Vec<f64> {
+    match p.len() {
+        0 | 1 => return vec![],
+        2 => {
+            if p[1].abs() < 1e-300 {
+                return vec![];
+            }
+            let x = -p[0] / p[1];
+            return if x >= a && x <= b { vec![x] } else { vec![] };
+        }
+        _ => {}
+    }
+
+    let derivative: Vec<f64> = p
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * i as f64)
+        .collect();
+    let mut bounds = vec![a];
+    bounds.extend(poly_roots_in(&derivative, a, b));
+    bounds.push(b);
+
+    let mut roots = vec![];
+    for w in bounds.windows(2) {
+        let (mut lo, mut hi) = (w[0], w[1]);
+        let (f_lo, f_hi) = (poly_eval(p, lo), poly_eval(p, hi));
+        if f_lo == 0.0 {
+            roots.push(lo);
+            continue;
+        }
+        if f_lo.signum() == f_hi.signum() {
+            continue;
+        }
+        for _ in 0..64 {
+            let mid = 0.5 * (lo + hi);
+            if mid <= lo || mid >= hi {
+                break;
+            }
+            if poly_eval(p, mid).signum() == f_lo.signum() {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        roots.push(0.5 * (lo + hi));
+    }
+    roots
+}
+
+/// Blobby iso-surface where the summed field of a set of charges equals
+/// `threshold`. Along a ray each charge contributes a degree-6 polynomial in
+/// `t`, so between the points where the ray enters or leaves a sphere of
+/// influence the field is one polynomial whose first crossing is found
+/// exactly. Normals come from the analytic gradient of the field.
+pub struct Metaballs {
+    charges: Vec<Charge>,
+    threshold: f64,
+    mp: Arc<dyn Material>,
+    bbox: Option<AABB>,
+}
+
+impl Metaballs {
+    pub fn new(charges: Vec<Charge>, threshold: f64, mp: Arc<dyn Material>) -> Self {
+        assert!(
+            threshold > 0.0,
+            "the iso-surface threshold must be positive"
+        );
+        // only charges pulling the field up can put surface inside their sphere
+        let bbox = charges
+            .iter()
+            .filter(|c| c.strength > 0.0)
+            .map(|c| AABB::new(c.center - c.radius, c.center + c.radius))
+            .fold(None, |acc: Option<AABB>, b| {
+                Some(match acc {
+                    Some(a) => surrounding_box(a, b),
+                    None => b,
+                })
+            });
+        Self {
+            charges,
+            threshold,
+            mp,
+            bbox,
+        }
+    }
+
+    pub fn field(&self, p: Point3) -> f64 {
+        self.charges
+            .iter()
+            .map(|c| {
+                let g = 1.0 - (p - c.center).squared_length() / (c.radius * c.radius);
+                if g > 0.0 {
+                    c.strength * g * g * g
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    /// Outward unit normal, against the gradient of the field. Where the
+    /// gradient vanishes there is no direction to take, and it faces up.
+    pub fn normal(&self, p: Point3) -> Vec3 {
+        let gradient = self.charges.iter().fold(Vec3::zero(), |acc, c| {
+            let r2 = c.radius * c.radius;
+            let g = 1.0 - (p - c.center).squared_length() / r2;
+            if g > 0.0 {
+                acc + (p - c.center) * (-6.0 * c.strength * g * g / r2)
+            } else {
+                acc
+            }
+        });
+        if gradient.squared_length() < 1e-300 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        -gradient.unit()
+    }
+}
+
+impl Object for Metaballs {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = self.bbox.as_ref()?.hit_range(ray, t_min, t_max)?;
+
+        // span of the ray inside each sphere of influence
+        let a = ray.dir.squared_length();
+        let spans: Vec<(f64, f64, &Charge)> = self
+            .charges
+            .iter()
+            .filter_map(|c| {
+                let oc = ray.orig - c.center;
+                let roots = solve_quadratic(
+                    a,
+                    2.0 * (oc * ray.dir),
+                    oc.squared_length() - c.radius * c.radius,
+                );
+                if roots.len() < 2 {
+                    return None;
+                }
+                let (t0, t1) = (roots[0].max(t_enter), roots[1].min(t_exit));
+                if t0 >= t1 {
+                    return None;
+                }
+                Some((t0, t1, c))
+            })
+            .collect();
+
+        let mut events: Vec<f64> = spans.iter().flat_map(|s| vec![s.0, s.1]).collect();
+        events.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+        for w in events.windows(2) {
+            let (lo, hi) = (w[0], w[1]);
+            if hi <= lo {
+                continue;
+            }
+            // the field as a polynomial in s = t - lo, with the falloff
+            // 1 - r^2 / radius^2 of each charge as a quadratic in s; taken
+            // from the ray origin instead, its terms would grow with the
+            // sixth power of the distance and cancel each other out
+            let mid = 0.5 * (lo + hi);
+            let start = ray.at(lo);
+            let mut poly = vec![-self.threshold];
+            for (t0, t1, c) in &spans {
+                if *t0 <= mid && mid <= *t1 {
+                    let oc = start - c.center;
+                    let r2 = c.radius * c.radius;
+                    let g = [
+                        1.0 - oc.squared_length() / r2,
+                        -2.0 * (oc * ray.dir) / r2,
+                        -a / r2,
+                    ];
+                    let cube = poly_mul(&poly_mul(&g, &g), &g);
+                    poly.resize(cube.len().max(poly.len()), 0.0);
+                    for (p, x) in poly.iter_mut().zip(cube) {
+                        *p += c.strength * x;
+                    }
+                }
+            }
+            if poly.len() == 1 {
+                continue;
+            }
+            let roots = poly_roots_in(&poly, 0.0, hi - lo);
+            if let Some(tin) = roots.iter().map(|s| lo + s).find(|&t| t > t_min) {
+                let p = ray.at(tin);
+                let outward_normal = self.normal(p);
+                let mut rec = HitRecord::new(p, outward_normal, tin, self.mp.clone());
+                rec.set_face_normal(ray, &outward_normal);
+                rec.set_uv(get_sphere_uv(&outward_normal));
+                return Some(rec);
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.bbox
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Color::ones()))
+    }
+
+    #[test]
+    fn test_single_charge() {
+        // (1 - r^2 / 4)^3 = 1 / 8 at r = sqrt(2)
+        let blob = Metaballs::new(vec![Charge::new(Point3::zero(), 2.0, 1.0)], 0.125, white());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = blob.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - (5.0 - 2.0_f64.sqrt())).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        // and on the way out from inside
+        let ray = Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let rec = blob.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 2.0_f64.sqrt()).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        // from far away the field is still solved near the surface
+        for &distance in &[100.0, 1000.0, 10000.0] {
+            let ray = Ray::new(
+                Point3::new(0.0, 0.0, distance),
+                Vec3::new(0.0, 0.0, -1.0),
+                0.0,
+            );
+            let rec = blob.hit(&ray, 0.001, 2.0 * distance).unwrap();
+            assert!((rec.t - (distance - 2.0_f64.sqrt())).abs() < 1e-9);
+            assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        }
+
+        // the field is flat at the center of the charge
+        assert_eq!(blob.normal(Point3::zero()), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_blend() {
+        let blobs = Metaballs::new(
+            vec![
+                Charge::new(Point3::new(-1.0, 0.0, 0.0), 2.0, 1.0),
+                Charge::new(Point3::new(1.0, 0.0, 0.0), 2.0, 1.0),
+            ],
+            0.5,
+            white(),
+        );
+        // each charge alone is below the threshold at the midpoint, together
+        // they bridge the gap
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = blobs.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((blobs.field(rec.p) - 0.5).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+
+        // the analytic normal matches the numeric gradient off-axis
+        let ray = Ray::new(Point3::new(0.7, 5.0, 0.3), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = blobs.hit(&ray, 0.001, 100.0).unwrap();
+        let h = 1e-6;
+        let numeric = -Vec3::new(
+            blobs.field(rec.p + Vec3::new(h, 0.0, 0.0))
+                - blobs.field(rec.p - Vec3::new(h, 0.0, 0.0)),
+            blobs.field(rec.p + Vec3::new(0.0, h, 0.0))
+                - blobs.field(rec.p - Vec3::new(0.0, h, 0.0)),
+            blobs.field(rec.p + Vec3::new(0.0, 0.0, h))
+                - blobs.field(rec.p - Vec3::new(0.0, 0.0, h)),
+        )
+        .unit();
+        assert!((rec.normal - numeric).length() < 1e-6);
+
+        let ray = Ray::new(Point3::new(0.0, 5.0, 1.9), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(blobs.hit(&ray, 0.001, 100.0).is_none());
+    }
+}
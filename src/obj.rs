@@ -0,0 +1,356 @@
+use crate::bvh::*;
+use crate::triangle::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(String, std::io::Error),
+    Parse {
+        file: String,
+        line: usize,
+        msg: String,
+    },
+    UnknownMaterial(String),
+    Empty(String),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(file, err) => write!(f, "cannot read {}: {}", file, err),
+            ObjError::Parse { file, line, msg } => write!(f, "{}:{}: {}", file, line, msg),
+            ObjError::UnknownMaterial(name) => write!(f, "material {:?} is not defined", name),
+            ObjError::Empty(file) => write!(f, "{} contains no faces", file),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Loads a Wavefront OBJ file (and the MTL libraries it references) as a
+/// list holding a single `BvhNode` over all of its triangles.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<HittableList, ObjError> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let src = std::fs::read_to_string(path).map_err(|e| ObjError::Io(name.clone(), e))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let triangles = parse_obj(&src, &name, base_dir)?;
+    if triangles.is_empty() {
+        return Err(ObjError::Empty(name));
+    }
+
+    let mut world = HittableList::default();
+    world.push(BvhNode::init(triangles, 0.0, 1.0, true));
+    Ok(world)
+}
+
+/// Loads every material of an MTL library, keyed by its `newmtl` name.
+pub fn load_mtl<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let src = std::fs::read_to_string(path).map_err(|e| ObjError::Io(name.clone(), e))?;
+    Ok(parse_mtl(&src, &name)?
+        .into_iter()
+        .map(|(mtl_name, mtl)| (mtl_name, mtl.to_material()))
+        .collect())
+}
+
+fn parse_obj(src: &str, name: &str, base_dir: &Path) -> Result<Vec<Arc<dyn Object>>, ObjError> {
+    let default_mat: Arc<dyn Material> = Arc::new(Lambertian::new(&(Color::ones() * 0.8)));
+    let mut materials = HashMap::new();
+    let mut cur_mat = default_mat;
+
+    let mut positions = vec![] as Vec<Point3>;
+    let mut normals = vec![] as Vec<Vec3>;
+    let mut texcoords = vec![] as Vec<(f64, f64)>;
+    let mut triangles = vec![] as Vec<Arc<dyn Object>>;
+
+    for (idx, raw_line) in src.lines().enumerate() {
+        let line_no = idx + 1;
+        let err = |msg: String| ObjError::Parse {
+            file: name.to_owned(),
+            line: line_no,
+            msg,
+        };
+        let line = raw_line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&args).map_err(err)?),
+            "vn" => normals.push(parse_vec3(&args).map_err(err)?),
+            "vt" => {
+                if args.is_empty() {
+                    return Err(err("vt needs at least one coordinate".to_owned()));
+                }
+                let u = parse_f64(args[0]).map_err(err)?;
+                let v = match args.get(1) {
+                    Some(v) => parse_f64(v).map_err(err)?,
+                    None => 0.0,
+                };
+                texcoords.push((u, v));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(err(format!(
+                        "face needs at least 3 vertices, got {}",
+                        args.len()
+                    )));
+                }
+                let mut corners = vec![];
+                for arg in &args {
+                    corners.push(
+                        parse_face_vertex(arg, positions.len(), texcoords.len(), normals.len())
+                            .map_err(err)?,
+                    );
+                }
+                // fan triangulation of (convex) polygons
+                for i in 1..corners.len() - 1 {
+                    let tri = [corners[0], corners[i], corners[i + 1]];
+                    let vertices = [
+                        positions[tri[0].0],
+                        positions[tri[1].0],
+                        positions[tri[2].0],
+                    ];
+                    let face_uvs = match (tri[0].1, tri[1].1, tri[2].1) {
+                        (Some(a), Some(b), Some(c)) => {
+                            Some([texcoords[a], texcoords[b], texcoords[c]])
+                        }
+                        _ => None,
+                    };
+                    let face_normals = match (tri[0].2, tri[1].2, tri[2].2) {
+                        (Some(a), Some(b), Some(c))
+                            if [a, b, c].iter().all(|&n| normals[n].squared_length() > 0.0) =>
+                        {
+                            Some([normals[a].unit(), normals[b].unit(), normals[c].unit()])
+                        }
+                        _ => None,
+                    };
+                    triangles.push(Arc::new(Triangle::new_with_attributes(
+                        vertices,
+                        face_normals,
+                        face_uvs,
+                        cur_mat.clone(),
+                    )));
+                }
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(err("mtllib needs a file name".to_owned()));
+                }
+                // file names may contain spaces
+                materials.extend(load_mtl(base_dir.join(args.join(" ")))?);
+            }
+            "usemtl" => {
+                let mtl_name = args.join(" ");
+                cur_mat = materials
+                    .get(&mtl_name)
+                    .cloned()
+                    .ok_or(ObjError::UnknownMaterial(mtl_name))?;
+            }
+            // groups, objects, smoothing groups and free-form geometry are ignored
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+/// Resolves one `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner into zero-based
+/// indices. Negative indices count back from the most recent element.
+fn parse_face_vertex(
+    arg: &str,
+    n_pos: usize,
+    n_tex: usize,
+    n_norm: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = arg.split('/');
+    let v = resolve_index(parts.next().unwrap_or(""), n_pos)?;
+    let vt = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve_index(s, n_tex)?),
+    };
+    let vn = match parts.next() {
+        Some("") | None => None,
+        Some(s) => Some(resolve_index(s, n_norm)?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("malformed face vertex {:?}", arg));
+    }
+    Ok((v, vt, vn))
+}
+
+fn resolve_index(s: &str, len: usize) -> Result<usize, String> {
+    let idx: i64 = s.parse().map_err(|_| format!("invalid index {:?}", s))?;
+    let resolved = if idx > 0 { idx - 1 } else { len as i64 + idx };
+    if idx == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(format!("index {} out of range (have {})", idx, len));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_f64(s: &str) -> Result<f64, String> {
+    s.parse().map_err(|_| format!("invalid number {:?}", s))
+}
+
+fn parse_vec3(args: &[&str]) -> Result<Vec3, String> {
+    if args.len() < 3 {
+        return Err(format!("expected 3 numbers, got {}", args.len()));
+    }
+    Ok(Vec3::new(
+        parse_f64(args[0])?,
+        parse_f64(args[1])?,
+        parse_f64(args[2])?,
+    ))
+}
+
+/// Raw parameters of one `newmtl` block.
+#[derive(Clone, Debug)]
+struct MtlMaterial {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ns: f64,
+    ni: f64,
+    dissolve: f64,
+    illum: u32,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            kd: Color::ones() * 0.8,
+            ks: Color::zero(),
+            ke: Color::zero(),
+            ns: 0.0,
+            ni: 1.5,
+            dissolve: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps the Phong-style MTL parameters onto the closest material we have:
+    /// emissive -> `DiffuseLight`, transparent -> `Dielectric`, reflective
+    /// (illum 3, or specular only) -> `Metal`, anything else -> `Lambertian`.
+    fn to_material(&self) -> Arc<dyn Material> {
+        let is_black = |c: &Color| c.x <= 0.0 && c.y <= 0.0 && c.z <= 0.0;
+
+        if !is_black(&self.ke) {
+            Arc::new(DiffuseLight::new_color(&self.ke))
+        } else if self.dissolve < 1.0 || [4, 6, 7, 9].contains(&self.illum) {
+            Arc::new(Dielectric::new(self.ni))
+        } else if !is_black(&self.ks) && (self.illum == 3 || is_black(&self.kd)) {
+            // Phong exponent to a roughness-like fuzz
+            let fuzz = (2.0 / (self.ns + 2.0)).sqrt();
+            Arc::new(Metal::new(&self.ks, fuzz))
+        } else {
+            Arc::new(Lambertian::new(&self.kd))
+        }
+    }
+}
+
+fn parse_mtl(src: &str, name: &str) -> Result<Vec<(String, MtlMaterial)>, ObjError> {
+    let mut materials = vec![] as Vec<(String, MtlMaterial)>;
+
+    for (idx, raw_line) in src.lines().enumerate() {
+        let line_no = idx + 1;
+        let err = |msg: String| ObjError::Parse {
+            file: name.to_owned(),
+            line: line_no,
+            msg,
+        };
+        let line = raw_line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(k) => k,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(err("newmtl needs a name".to_owned()));
+            }
+            materials.push((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let cur = match materials.last_mut() {
+            Some((_, mtl)) => mtl,
+            None => return Err(err(format!("{} before any newmtl", keyword))),
+        };
+        let scalar = |args: &[&str]| match args.first() {
+            Some(s) => parse_f64(s),
+            None => Err("expected a number".to_owned()),
+        };
+        match keyword {
+            "Kd" => cur.kd = parse_vec3(&args).map_err(err)?,
+            "Ks" => cur.ks = parse_vec3(&args).map_err(err)?,
+            "Ke" => cur.ke = parse_vec3(&args).map_err(err)?,
+            "Ns" => cur.ns = scalar(&args).map_err(err)?,
+            "Ni" => cur.ni = scalar(&args).map_err(err)?,
+            "d" => cur.dissolve = scalar(&args).map_err(err)?,
+            "Tr" => cur.dissolve = 1.0 - scalar(&args).map_err(err)?,
+            "illum" => {
+                cur.illum = args
+                    .first()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| err("illum needs an integer".to_owned()))?
+            }
+            // texture maps and other statements are not supported yet
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_faces() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\n\
+                   f 1//1 2//1 3//1 4//1\nf -4 -3 -2\n";
+        let tris = parse_obj(src, "quad.obj", Path::new("")).unwrap();
+        assert_eq!(tris.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_error_line() {
+        let src = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+        match parse_obj(src, "bad.obj", Path::new("")) {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 3),
+            _ => panic!("expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn test_unknown_material() {
+        let src = "v 0 0 0\nusemtl missing\n";
+        assert!(matches!(
+            parse_obj(src, "bad.obj", Path::new("")),
+            Err(ObjError::UnknownMaterial(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_mtl() {
+        let src = "newmtl glass\nKd 1 1 1\nd 0.2\nNi 1.33\nnewmtl lamp\nKe 4 4 4\n";
+        let mtls = parse_mtl(src, "test.mtl").unwrap();
+        assert_eq!(mtls.len(), 2);
+        assert_eq!(mtls[0].0, "glass");
+        assert!((mtls[0].1.ni - 1.33).abs() < 1e-12);
+        assert_eq!(mtls[1].1.ke, Color::new(4.0, 4.0, 4.0));
+    }
+}
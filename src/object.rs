@@ -9,8 +9,40 @@ pub trait Object: Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB>;
     fn get_background(&self, t: f64) -> Color;
+
+    /// Whether anything is hit between `t_min` and `t_max`, for shadow rays
+    /// that don't need to know what or where. Objects that can answer this
+    /// without building a `HitRecord` should override it.
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit(ray, t_min, t_max).is_some()
+    }
+
+    /// Every surface crossing along the whole (infinite) line of `ray`, in
+    /// order. `front_face` of each record tells whether the ray enters or
+    /// leaves the object there. The default walks along the line with `hit`;
+    /// `Csg` overrides it to merge its children's crossings.
+    fn crossings(&self, ray: &Ray) -> Vec<HitRecord> {
+        let mut res = vec![] as Vec<HitRecord>;
+        let mut t_min = -std::f64::INFINITY;
+
+        while res.len() < MAX_CROSSINGS {
+            match self.hit(ray, t_min, std::f64::INFINITY) {
+                Some(rec) => {
+                    t_min = rec.t + 1e-7 * rec.t.abs().max(1.0);
+                    res.push(rec);
+                }
+                None => break,
+            }
+        }
+        res
+    }
 }
 
+/// Upper bound on the surface crossings collected by `Object::crossings`,
+/// so that an object which is not closed cannot stall the walk along the
+/// ray.
+const MAX_CROSSINGS: usize = 64;
+
 #[derive(Clone)]
 pub struct Sphere {
     pub center: Point3,
@@ -77,9 +109,105 @@ impl Object for Sphere {
     fn get_background(&self, _t: f64) -> Color {
         Color::zero()
     }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let oc = ray.orig - self.center;
+        let a = ray.dir.squared_length();
+        let half_b = oc * ray.dir;
+        let c = oc.squared_length() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return false;
+        }
+        let root = discriminant.sqrt();
+        let in_range = |t: f64| t < t_max && t > t_min;
+        in_range((-half_b - root) / a) || in_range((-half_b + root) / a)
+    }
+}
+
+/// Sphere whose center moves linearly from `center0` at `time0` to
+/// `center1` at `time1`.
+#[derive(Clone)]
+pub struct MovingSphere {
+    pub center0: Point3,
+    pub center1: Point3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub mat_ptr: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new(
+        c0: Point3,
+        c1: Point3,
+        time0: f64,
+        time1: f64,
+        r: f64,
+        m: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center0: c0,
+            center1: c1,
+            time0,
+            time1,
+            radius: r,
+            mat_ptr: m,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Point3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+        self.center0
+            + (self.center1 - self.center0) * ((time - self.time0) / (self.time1 - self.time0))
+    }
 }
 
-fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
+impl Object for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let center = self.center(ray.tm);
+        let oc = ray.orig - center;
+        let a = ray.dir.squared_length();
+        let half_b = oc * ray.dir;
+        let c = oc.squared_length() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+
+        if discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            for &temp in &[(-half_b - root) / a, (-half_b + root) / a] {
+                if temp < t_max && temp > t_min {
+                    let outward_normal = (ray.at(temp) - center) / self.radius;
+                    let mut rec =
+                        HitRecord::new(ray.at(temp), outward_normal, temp, self.mat_ptr.clone());
+                    rec.set_face_normal(ray, &outward_normal);
+                    rec.set_uv(get_sphere_uv(&outward_normal));
+                    return Some(rec);
+                }
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        let box0 = AABB::new(
+            self.center(t0) - Vec3::ones() * self.radius,
+            self.center(t0) + Vec3::ones() * self.radius,
+        );
+        let box1 = AABB::new(
+            self.center(t1) - Vec3::ones() * self.radius,
+            self.center(t1) + Vec3::ones() * self.radius,
+        );
+        Some(surrounding_box(box0, box1))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+pub fn get_sphere_uv(p: &Vec3) -> (f64, f64) {
     let phi = p.z.atan2(p.x);
     let theta = p.y.asin();
     let u = 1.0 - (phi + std::f64::consts::PI) / (2.0 * std::f64::consts::PI);
@@ -96,6 +224,11 @@ pub struct HitRecord {
     pub u: f64,
     pub v: f64,
     pub front_face: bool,
+    /// Interpolated vertex color, for geometry that carries one.
+    pub color: Option<Color>,
+    /// Unit direction along the surface for fiber-like geometry such as
+    /// curves, where shading depends on the strand direction.
+    pub tangent: Option<Vec3>,
 }
 
 impl HitRecord {
@@ -116,6 +249,8 @@ impl HitRecord {
             u: 0.0,
             v: 0.0,
             front_face: true,
+            color: None,
+            tangent: None,
         }
     }
 
@@ -175,6 +310,12 @@ impl Object for HittableList {
         cur_rec
     }
 
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.objects
+            .iter()
+            .any(|object| object.occluded(ray, t_min, t_max))
+    }
+
     fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
         if self.objects.is_empty() {
             return None;
@@ -234,10 +375,10 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
         let scatter_direction = rec.normal + crate::vec3::random_unit_vector();
-        let scattered = Ray::new(rec.p, scatter_direction);
-        let attenuation = self.albedo.value(rec.u, rec.v, &rec.p);
+        let scattered = Ray::new(rec.p, scatter_direction, r_in.tm);
+        let attenuation = self.albedo.value_at(rec);
         Some((attenuation, scattered))
     }
 
@@ -270,6 +411,7 @@ impl Material for Metal {
         let scattered = Ray::new(
             rec.p,
             reflected + crate::vec3::random_in_unit_sphere() * self.fuzz,
+            r_in.tm,
         );
         let attenuation = self.albedo;
         if scattered.dir * rec.normal > 0.0 {
@@ -316,18 +458,18 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         if etai_over_etat * sin_theta > 1.0 {
             let reflected = crate::vec3::reflect(&unit_direction, &rec.normal);
-            let scattered = Ray::new(rec.p, reflected);
+            let scattered = Ray::new(rec.p, reflected, r_in.tm);
             return Some((attenuation, scattered));
         }
         let reflect_prob = schlick(cos_theta, etai_over_etat);
         let flag: f64 = rand::thread_rng().gen();
         if flag < reflect_prob {
             let reflected = crate::vec3::reflect(&unit_direction, &rec.normal);
-            let scattered = Ray::new(rec.p, reflected);
+            let scattered = Ray::new(rec.p, reflected, r_in.tm);
             return Some((attenuation, scattered));
         }
         let refracted = crate::vec3::refract(&unit_direction, &rec.normal, etai_over_etat);
-        let scattered = Ray::new(rec.p, refracted);
+        let scattered = Ray::new(rec.p, refracted, r_in.tm);
         Some((attenuation, scattered))
     }
 
@@ -361,3 +503,33 @@ impl Material for DiffuseLight {
         self.emit.value(u, v, p)
     }
 }
+
+/// Phase function of a participating medium: scatters uniformly in all
+/// directions.
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(a: Arc<dyn Texture>) -> Self {
+        Self { albedo: a }
+    }
+
+    pub fn new_color(c: &Color) -> Self {
+        Self {
+            albedo: Arc::new(SolidColor::new(c)),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let scattered = Ray::new(rec.p, crate::vec3::random_in_unit_sphere(), r_in.tm);
+        let attenuation = self.albedo.value_at(rec);
+        Some((attenuation, scattered))
+    }
+
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::zero()
+    }
+}
@@ -0,0 +1,539 @@
+use crate::bvh::*;
+use crate::triangle::*;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(String, std::io::Error),
+    Header {
+        line: usize,
+        msg: String,
+    },
+    Data {
+        element: String,
+        index: usize,
+        msg: String,
+    },
+    Empty,
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlyError::Io(file, err) => write!(f, "cannot read {}: {}", file, err),
+            PlyError::Header { line, msg } => write!(f, "header line {}: {}", line, msg),
+            PlyError::Data {
+                element,
+                index,
+                msg,
+            } => write!(f, "{} #{}: {}", element, index, msg),
+            PlyError::Empty => write!(f, "mesh contains no faces"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+/// Loads an ASCII or binary (either endianness) PLY mesh as a list holding
+/// a single `BvhNode` over its triangles. Meshes with vertex colors get a
+/// `Lambertian` over a `VertexColorTexture`, others a plain gray one.
+pub fn load_ply<P: AsRef<Path>>(path: P) -> Result<HittableList, PlyError> {
+    load(path.as_ref(), None)
+}
+
+/// Like `load_ply`, with every face using `mat`.
+pub fn load_ply_with_material<P: AsRef<Path>>(
+    path: P,
+    mat: Arc<dyn Material>,
+) -> Result<HittableList, PlyError> {
+    load(path.as_ref(), Some(mat))
+}
+
+fn load(path: &Path, mat: Option<Arc<dyn Material>>) -> Result<HittableList, PlyError> {
+    let data = std::fs::read(path).map_err(|e| PlyError::Io(path.display().to_string(), e))?;
+    let mesh = parse_ply(&data, mat)?;
+    if mesh.faces.is_empty() {
+        return Err(PlyError::Empty);
+    }
+
+    let mut world = HittableList::default();
+    world.push(BvhNode::init(mesh.into_triangles(), 0.0, 1.0, true));
+    Ok(world)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// What a color channel stored as this type is divided by: integers
+    /// span their whole range, floats are already in [0, 1].
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::I8 => std::i8::MAX as f64,
+            Scalar::U8 => std::u8::MAX as f64,
+            Scalar::I16 => std::i16::MAX as f64,
+            Scalar::U16 => std::u16::MAX as f64,
+            Scalar::I32 => std::i32::MAX as f64,
+            Scalar::U32 => std::u32::MAX as f64,
+            Scalar::F32 | Scalar::F64 => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    /// Length-prefixed list: (name, count type, item type).
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// Byte offset of the body.
+    body: usize,
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, PlyError> {
+    let mut format = None;
+    let mut elements = vec![] as Vec<Element>;
+    let mut pos = 0;
+    let mut line_no = 0;
+
+    loop {
+        line_no += 1;
+        let err = |msg: &str| PlyError::Header {
+            line: line_no,
+            msg: msg.to_owned(),
+        };
+        let end = match data[pos..].iter().position(|&b| b == b'\n') {
+            Some(end) => pos + end,
+            None => return Err(err("missing end_header")),
+        };
+        let line = std::str::from_utf8(&data[pos..end])
+            .map_err(|_| err("header is not valid text"))?
+            .trim();
+        pos = end + 1;
+
+        if line_no == 1 {
+            if line != "ply" {
+                return Err(err("not a PLY file"));
+            }
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"format") => {
+                format = Some(match tokens.get(1) {
+                    Some(&"ascii") => Format::Ascii,
+                    Some(&"binary_little_endian") => Format::BinaryLittleEndian,
+                    Some(&"binary_big_endian") => Format::BinaryBigEndian,
+                    _ => return Err(err("unknown format")),
+                });
+            }
+            Some(&"element") => {
+                if tokens.len() != 3 {
+                    return Err(err("expected `element <name> <count>`"));
+                }
+                let count = tokens[2]
+                    .parse()
+                    .map_err(|_| err("invalid element count"))?;
+                elements.push(Element {
+                    name: tokens[1].to_owned(),
+                    count,
+                    properties: vec![],
+                });
+            }
+            Some(&"property") => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| err("property before any element"))?;
+                let property = match tokens.as_slice() {
+                    ["property", "list", count_ty, item_ty, name] => Property::List(
+                        (*name).to_owned(),
+                        Scalar::parse(count_ty).ok_or_else(|| err("unknown list count type"))?,
+                        Scalar::parse(item_ty).ok_or_else(|| err("unknown list item type"))?,
+                    ),
+                    ["property", ty, name] => Property::Scalar(
+                        (*name).to_owned(),
+                        Scalar::parse(ty).ok_or_else(|| err("unknown property type"))?,
+                    ),
+                    _ => return Err(err("malformed property")),
+                };
+                element.properties.push(property);
+            }
+            Some(&"end_header") => break,
+            Some(&"comment") | Some(&"obj_info") | None => {}
+            Some(_) => return Err(err("unknown header keyword")),
+        }
+    }
+
+    Ok(Header {
+        format: format.ok_or(PlyError::Header {
+            line: line_no,
+            msg: "missing format line".to_owned(),
+        })?,
+        elements,
+        body: pos,
+    })
+}
+
+/// Sequential reader over the body in any of the three encodings.
+struct BodyReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl<'a> BodyReader<'a> {
+    fn next_token(&mut self) -> Result<&'a str, String> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err("unexpected end of file".to_owned());
+        }
+        std::str::from_utf8(&self.data[start..self.pos]).map_err(|_| "invalid text".to_owned())
+    }
+
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.next_token()?;
+            return token
+                .parse()
+                .map_err(|_| format!("invalid number {:?}", token));
+        }
+
+        let size = ty.size();
+        if self.pos + size > self.data.len() {
+            return Err("unexpected end of file".to_owned());
+        }
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.data[self.pos..self.pos + size]);
+        self.pos += size;
+        if self.format == Format::BinaryBigEndian {
+            bytes[..size].reverse();
+        }
+
+        // bytes are little-endian from here on
+        Ok(match ty {
+            Scalar::I8 => bytes[0] as i8 as f64,
+            Scalar::U8 => bytes[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(bytes),
+        })
+    }
+}
+
+/// Decodes a PLY file into a `TriangleMesh`. Polygons are fan-triangulated;
+/// elements other than `vertex` and `face` are skipped.
+fn parse_ply(data: &[u8], mat: Option<Arc<dyn Material>>) -> Result<TriangleMesh, PlyError> {
+    let header = parse_header(data)?;
+    let mut reader = BodyReader {
+        data,
+        pos: header.body,
+        format: header.format,
+    };
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut colors = vec![];
+    let mut faces = vec![] as Vec<[u32; 3]>;
+    let (mut has_normals, mut has_uvs, mut has_colors) = (false, false, false);
+
+    for element in &header.elements {
+        let names: Vec<&str> = element
+            .properties
+            .iter()
+            .map(|prop| match prop {
+                Property::Scalar(name, _) | Property::List(name, _, _) => name.as_str(),
+            })
+            .collect();
+        let has = |wanted: &[&str]| wanted.iter().all(|w| names.contains(w));
+        if element.name == "vertex" {
+            has_normals = has(&["nx", "ny", "nz"]);
+            has_uvs = has(&["u", "v"]) || has(&["s", "t"]) || has(&["texture_u", "texture_v"]);
+            has_colors = has(&["red", "green", "blue"]);
+        }
+
+        for index in 0..element.count {
+            let err = |msg: String| PlyError::Data {
+                element: element.name.clone(),
+                index,
+                msg,
+            };
+            let mut pos = Point3::zero();
+            let mut normal = Vec3::zero();
+            let mut uv = (0.0, 0.0);
+            let mut color = Color::zero();
+
+            for prop in &element.properties {
+                match prop {
+                    Property::Scalar(name, ty) => {
+                        let val = reader.read(*ty).map_err(err)?;
+                        match name.as_str() {
+                            "x" => pos.x = val,
+                            "y" => pos.y = val,
+                            "z" => pos.z = val,
+                            "nx" => normal.x = val,
+                            "ny" => normal.y = val,
+                            "nz" => normal.z = val,
+                            "u" | "s" | "texture_u" => uv.0 = val,
+                            "v" | "t" | "texture_v" => uv.1 = val,
+                            "red" => color.x = val / ty.color_scale(),
+                            "green" => color.y = val / ty.color_scale(),
+                            "blue" => color.z = val / ty.color_scale(),
+                            _ => {}
+                        }
+                    }
+                    Property::List(name, count_ty, item_ty) => {
+                        let count = reader.read(*count_ty).map_err(err)? as usize;
+                        // the count comes from the file, so items are only
+                        // stored as far as the data actually goes
+                        let mut items = vec![];
+                        for _ in 0..count {
+                            items.push(reader.read(*item_ty).map_err(err)?);
+                        }
+                        let is_face_list = name == "vertex_indices" || name == "vertex_index";
+                        if element.name == "face" && is_face_list {
+                            if count < 3 {
+                                return Err(err(format!("face has only {} vertices", count)));
+                            }
+                            // float index types are allowed, fractions are not
+                            if let Some(&i) =
+                                items.iter().find(|&&i| !(i >= 0.0 && i.fract() == 0.0))
+                            {
+                                return Err(err(format!("invalid vertex index {}", i)));
+                            }
+                            for i in 1..count - 1 {
+                                faces.push([items[0] as u32, items[i] as u32, items[i + 1] as u32]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if element.name == "vertex" {
+                positions.push(pos);
+                if has_normals {
+                    normals.push(normal);
+                }
+                if has_uvs {
+                    uvs.push(uv);
+                }
+                if has_colors {
+                    colors.push(color);
+                }
+            }
+        }
+    }
+
+    if let Some(face) = faces
+        .iter()
+        .position(|f| f.iter().any(|&i| i as usize >= positions.len()))
+    {
+        return Err(PlyError::Data {
+            element: "face".to_owned(),
+            index: face,
+            msg: format!("vertex index out of range (have {})", positions.len()),
+        });
+    }
+
+    let mat_ptr = mat.unwrap_or_else(|| {
+        let gray = Arc::new(SolidColor::new(&(Color::ones() * 0.8)));
+        if has_colors {
+            Arc::new(Lambertian::new_arc(Arc::new(VertexColorTexture::new(gray))))
+        } else {
+            Arc::new(Lambertian::new_arc(gray))
+        }
+    });
+
+    Ok(TriangleMesh {
+        positions,
+        normals: if has_normals { Some(normals) } else { None },
+        uvs: if has_uvs { Some(uvs) } else { None },
+        colors: if has_colors { Some(colors) } else { None },
+        faces,
+        mat_ptr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4\nproperty float x\nproperty float y\n\
+                          property float z\nproperty uchar red\nproperty uchar green\n\
+                          property uchar blue\nelement face 1\n\
+                          property list uchar int vertex_indices\nend_header\n";
+
+    fn binary_body(big_endian: bool) -> Vec<u8> {
+        let mut body = vec![];
+        let corners = [(0.0f32, 0.0f32), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for &(x, y) in &corners {
+            for &val in &[x, y, 0.0f32] {
+                if big_endian {
+                    body.extend_from_slice(&val.to_be_bytes());
+                } else {
+                    body.extend_from_slice(&val.to_le_bytes());
+                }
+            }
+            body.extend_from_slice(&[255, 0, 51]);
+        }
+        body.push(4);
+        for &idx in &[0i32, 1, 2, 3] {
+            if big_endian {
+                body.extend_from_slice(&idx.to_be_bytes());
+            } else {
+                body.extend_from_slice(&idx.to_le_bytes());
+            }
+        }
+        body
+    }
+
+    fn check_quad(mesh: &TriangleMesh) {
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.positions[2], Point3::new(1.0, 1.0, 0.0));
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        let colors = mesh.colors.as_ref().unwrap();
+        assert_eq!(colors[0], Color::new(1.0, 0.0, 0.2));
+        assert!(mesh.normals.is_none());
+    }
+
+    #[test]
+    fn test_ascii() {
+        let src = format!(
+            "ply\nformat ascii 1.0\ncomment test\n{}\
+             0 0 0 255 0 51\n1 0 0 255 0 51\n1 1 0 255 0 51\n0 1 0 255 0 51\n4 0 1 2 3\n",
+            HEADER
+        );
+        check_quad(&parse_ply(src.as_bytes(), None).unwrap());
+    }
+
+    #[test]
+    fn test_binary() {
+        for &(format, big_endian) in &[("binary_little_endian", false), ("binary_big_endian", true)]
+        {
+            let mut data = format!("ply\r\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+            data.extend(binary_body(big_endian));
+            check_quad(&parse_ply(&data, None).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_color_types() {
+        // 16-bit and float colors end up in [0, 1] as well
+        for &(ty, value) in &[("ushort", "65535"), ("float", "1.0")] {
+            let src = format!(
+                "ply\nformat ascii 1.0\n{}\
+                 0 0 0 {v} 0 0\n1 0 0 {v} 0 0\n1 1 0 {v} 0 0\n0 1 0 {v} 0 0\n3 0 1 2\n",
+                HEADER.replace("uchar red", &format!("{} red", ty)),
+                v = value
+            );
+            let mesh = parse_ply(src.as_bytes(), None).unwrap();
+            assert_eq!(mesh.colors.unwrap()[0], Color::new(1.0, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            parse_ply(b"ply\nformat ascii 1.0\nelement vertex x\n", None),
+            Err(PlyError::Header { line: 3, .. })
+        ));
+        let src = format!("ply\nformat ascii 1.0\n{}0 0 0 255 0 51\n", HEADER);
+        assert!(matches!(
+            parse_ply(src.as_bytes(), None),
+            Err(PlyError::Data { index: 1, .. })
+        ));
+
+        let src = format!(
+            "ply\nformat ascii 1.0\n{}\
+             0 0 0 255 0 51\n1 0 0 255 0 51\n1 1 0 255 0 51\n0 1 0 255 0 51\n3 0 -1 2\n",
+            HEADER
+        );
+        assert!(matches!(
+            parse_ply(src.as_bytes(), None),
+            Err(PlyError::Data { index: 0, .. })
+        ));
+
+        let header = HEADER.replace("list uchar int", "list uchar float");
+        let src = format!(
+            "ply\nformat ascii 1.0\n{}\
+             0 0 0 255 0 51\n1 0 0 255 0 51\n1 1 0 255 0 51\n0 1 0 255 0 51\n3 0 1.5 2\n",
+            header
+        );
+        assert!(matches!(
+            parse_ply(src.as_bytes(), None),
+            Err(PlyError::Data { index: 0, .. })
+        ));
+
+        // a list claiming about 4e9 items runs out of data instead of memory
+        let header = HEADER.replace("list uchar int", "list uint int");
+        let mut data = format!("ply\nformat binary_little_endian 1.0\n{}", header).into_bytes();
+        data.extend(&binary_body(false)[..4 * 15]);
+        data.extend_from_slice(&4_000_000_000u32.to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        assert!(matches!(
+            parse_ply(&data, None),
+            Err(PlyError::Data { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+pub use crate::object::*;
+
+const LEAF_SIZE: usize = 4;
+
+/// One scanned point, rendered as a disc facing along `normal`. Stored in
+/// single precision with an 8-bit color so that clouds of tens of millions
+/// of points stay within memory.
+#[derive(Copy, Clone, Debug)]
+pub struct Surfel {
+    position: [f32; 3],
+    normal: [f32; 3],
+    color: Option<[u8; 3]>,
+}
+
+impl Surfel {
+    pub fn new(position: Point3, normal: Vec3) -> Self {
+        let n = normal.unit();
+        Self {
+            position: [position.x as f32, position.y as f32, position.z as f32],
+            normal: [n.x as f32, n.y as f32, n.z as f32],
+            color: None,
+        }
+    }
+
+    /// Surfel with a color in [0, 1], handed to the material through
+    /// `HitRecord::color` (see `VertexColorTexture`).
+    pub fn new_color(position: Point3, normal: Vec3, color: Color) -> Self {
+        let channel = |c: f64| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+        Self {
+            color: Some([channel(color.x), channel(color.y), channel(color.z)]),
+            ..Self::new(position, normal)
+        }
+    }
+
+    fn position(&self) -> Point3 {
+        let p = self.position;
+        Point3::new(p[0] as f64, p[1] as f64, p[2] as f64)
+    }
+
+    fn normal(&self) -> Vec3 {
+        let n = self.normal;
+        Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64)
+    }
+
+    fn bounding_box(&self, radius: f64) -> AABB {
+        // half extent of the tilted disc along each axis
+        let n = self.normal();
+        let extent = Vec3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        ) * radius
+            + 0.0001;
+        AABB::new(self.position() - extent, self.position() + extent)
+    }
+}
+
+/// Node of the flattened tree. Leaves own `count` surfels from `start`;
+/// inner nodes (`count == 0`) have their first child right after them and
+/// the second at `start`.
+struct CloudNode {
+    bbox: AABB,
+    start: u32,
+    count: u32,
+}
+
+/// Point cloud whose points are discs of a common `radius`. The surfels are
+/// reordered so that every node of the internal BVH covers a contiguous run
+/// of them, which keeps the tree to a plain array of boxes and ranges
+/// instead of one `Arc<dyn Object>` per point.
+pub struct PointCloud {
+    surfels: Vec<Surfel>,
+    nodes: Vec<CloudNode>,
+    radius: f64,
+    mp: Arc<dyn Material>,
+}
+
+impl PointCloud {
+    pub fn new(mut surfels: Vec<Surfel>, radius: f64, mp: Arc<dyn Material>) -> Self {
+        assert!(
+            !surfels.is_empty(),
+            "a point cloud needs at least one point"
+        );
+        assert!(
+            surfels.len() <= std::u32::MAX as usize,
+            "too many points for one cloud"
+        );
+        let mut nodes = Vec::with_capacity(2 * surfels.len() / LEAF_SIZE + 1);
+        Self::build(&mut surfels, 0, radius, &mut nodes);
+        Self {
+            surfels,
+            nodes,
+            radius,
+            mp,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.surfels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.surfels.is_empty()
+    }
+
+    /// Appends the subtree over `surfels`, which start at `offset` in the
+    /// whole cloud, and returns its bounding box.
+    fn build(
+        surfels: &mut [Surfel],
+        offset: usize,
+        radius: f64,
+        nodes: &mut Vec<CloudNode>,
+    ) -> AABB {
+        let index = nodes.len();
+        nodes.push(CloudNode {
+            bbox: surfels[0].bounding_box(radius),
+            start: offset as u32,
+            count: surfels.len() as u32,
+        });
+        if surfels.len() <= LEAF_SIZE {
+            let bbox = surfels[1..].iter().fold(nodes[index].bbox, |acc, s| {
+                surrounding_box(acc, s.bounding_box(radius))
+            });
+            nodes[index].bbox = bbox;
+            return bbox;
+        }
+
+        // split the centers at the middle of their longest extent
+        let (lo, hi) = surfels.iter().fold(
+            ([std::f32::INFINITY; 3], [std::f32::NEG_INFINITY; 3]),
+            |(mut lo, mut hi), s| {
+                for axis in 0..3 {
+                    lo[axis] = lo[axis].min(s.position[axis]);
+                    hi[axis] = hi[axis].max(s.position[axis]);
+                }
+                (lo, hi)
+            },
+        );
+        let axis = (0..3)
+            .max_by(|&a, &b| (hi[a] - lo[a]).partial_cmp(&(hi[b] - lo[b])).unwrap())
+            .unwrap();
+        let middle = 0.5 * (lo[axis] + hi[axis]);
+        let mut mid = 0;
+        for i in 0..surfels.len() {
+            if surfels[i].position[axis] < middle {
+                surfels.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == surfels.len() {
+            // all centers coincide (or nearly so)
+            mid = surfels.len() / 2;
+        }
+
+        let (left, right) = surfels.split_at_mut(mid);
+        let left_box = Self::build(left, offset, radius, nodes);
+        nodes[index].start = nodes.len() as u32;
+        nodes[index].count = 0;
+        let right_box = Self::build(right, offset + mid, radius, nodes);
+        let bbox = surrounding_box(left_box, right_box);
+        nodes[index].bbox = bbox;
+        bbox
+    }
+
+    fn hit_surfel(&self, surfel: &Surfel, ray: &Ray, t_min: f64, t_max: f64) -> Option<f64> {
+        let normal = surfel.normal();
+        let denom = ray.dir * normal;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let center = surfel.position();
+        let tin = (center - ray.orig) * normal / denom;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+        if (ray.at(tin) - center).squared_length() > self.radius * self.radius {
+            return None;
+        }
+        Some(tin)
+    }
+}
+
+impl Object for PointCloud {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest = None as Option<(f64, usize)>;
+        let mut t_max = t_max;
+        let mut stack = Vec::with_capacity(64);
+        if let Some((t_enter, _)) = self.nodes[0].bbox.hit_range(ray, t_min, t_max) {
+            stack.push((0, t_enter));
+        }
+
+        while let Some((index, t_enter)) = stack.pop() {
+            if t_enter > t_max {
+                continue;
+            }
+            let node = &self.nodes[index];
+            let start = node.start as usize;
+            if node.count > 0 {
+                for i in start..start + node.count as usize {
+                    if let Some(tin) = self.hit_surfel(&self.surfels[i], ray, t_min, t_max) {
+                        t_max = tin;
+                        closest = Some((tin, i));
+                    }
+                }
+                continue;
+            }
+
+            // visit the nearer child first so that its hits cut off the other
+            let first = self.nodes[index + 1].bbox.hit_range(ray, t_min, t_max);
+            let second = self.nodes[start].bbox.hit_range(ray, t_min, t_max);
+            let mut children = [(index + 1, first), (start, second)];
+            if let (Some(a), Some(b)) = (first, second) {
+                if b.0 < a.0 {
+                    children.swap(0, 1);
+                }
+            }
+            for &(child, range) in children.iter().rev() {
+                if let Some((t_enter, _)) = range {
+                    stack.push((child, t_enter));
+                }
+            }
+        }
+
+        let (tin, i) = closest?;
+        let surfel = &self.surfels[i];
+        let outward_normal = surfel.normal();
+        let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &outward_normal);
+        rec.color = surfel
+            .color
+            .map(|c| Color::new(c[0] as f64, c[1] as f64, c[2] as f64) / 255.0);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.nodes[0].bbox)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn grid() -> PointCloud {
+        // 20 x 20 points on the plane z = 0 and a red one floating above
+        let mut surfels: Vec<Surfel> = (0..400)
+            .map(|i| {
+                Surfel::new_color(
+                    Point3::new((i % 20) as f64 * 0.1, (i / 20) as f64 * 0.1, 0.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                    Color::ones(),
+                )
+            })
+            .collect();
+        surfels.push(Surfel::new_color(
+            Point3::new(1.0, 1.0, 0.5),
+            Vec3::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 0.0, 0.0),
+        ));
+        PointCloud::new(
+            surfels,
+            0.06,
+            Arc::new(Lambertian::new_arc(Arc::new(VertexColorTexture::new(
+                Arc::new(SolidColor::new(&Color::ones())),
+            )))),
+        )
+    }
+
+    #[test]
+    fn test_nearest_surfel() {
+        let cloud = grid();
+        assert_eq!(cloud.len(), 401);
+        let ray = Ray::new(Point3::new(1.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = cloud.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-6);
+        assert_eq!(rec.color, Some(Color::new(1.0, 0.0, 0.0)));
+
+        let ray = Ray::new(Point3::new(0.52, 0.33, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = cloud.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-6);
+        assert_eq!(rec.color, Some(Color::ones()));
+
+        // between the discs
+        let ray = Ray::new(Point3::new(0.55, 0.35, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(cloud.hit(&ray, 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let cloud = grid();
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let orig = Point3::new(rng.gen_range(-1.0, 3.0), rng.gen_range(-1.0, 3.0), 3.0);
+            let target = Point3::new(rng.gen_range(0.0, 2.0), rng.gen_range(0.0, 2.0), 0.0);
+            let ray = Ray::new(orig, target - orig, 0.0);
+            let expected = cloud
+                .surfels
+                .iter()
+                .filter_map(|s| cloud.hit_surfel(s, &ray, 0.001, 100.0))
+                .fold(None, |acc: Option<f64>, t| {
+                    Some(acc.map_or(t, |a| a.min(t)))
+                });
+            let found = cloud.hit(&ray, 0.001, 100.0).map(|rec| rec.t);
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+pub use crate::object::*;
+
+/// Parallelogram spanned by the edges `u` and `v` from corner `q`. Unlike
+/// the axis-aligned rects it can face any direction, which makes it handy
+/// for tilted panels and area lights.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    mp: Arc<dyn Material>,
+    normal: Vec3,
+    d: f64,
+    w: Vec3,
+    bbox: AABB,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mp: Arc<dyn Material>) -> Self {
+        let n = Vec3::cross(u, v);
+        let normal = n.unit();
+        let d = normal * q;
+        let w = n / (n * n);
+
+        let bbox = surrounding_box(AABB::new(q, q + u + v), AABB::new(q + u, q + v));
+        Self {
+            q,
+            u,
+            v,
+            mp,
+            normal,
+            d,
+            w,
+            bbox: pad_box(bbox),
+        }
+    }
+}
+
+/// Widens every side of `b` thinner than a small delta, so that planar
+/// shapes lying in an axis plane still get a box the slab test can hit.
+fn pad_box(b: AABB) -> AABB {
+    let delta = 0.0001;
+    let pad = |lo: f64, hi: f64| {
+        if hi - lo < delta {
+            (lo - delta / 2.0, hi + delta / 2.0)
+        } else {
+            (lo, hi)
+        }
+    };
+    let (x0, x1) = pad(b.min_p.x, b.max_p.x);
+    let (y0, y1) = pad(b.min_p.y, b.max_p.y);
+    let (z0, z1) = pad(b.min_p.z, b.max_p.z);
+    AABB::new(Point3::new(x0, y0, z0), Point3::new(x1, y1, z1))
+}
+
+impl Object for Quad {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.normal * ray.dir;
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let tin = (self.d - self.normal * ray.orig) / denom;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+
+        // planar coordinates of the hit point in the (u, v) frame
+        let planar_hitpt_vector = ray.at(tin) - self.q;
+        let alpha = self.w * Vec3::cross(planar_hitpt_vector, self.v);
+        let beta = self.w * Vec3::cross(self.u, planar_hitpt_vector);
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord::new(ray.at(tin), self.normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &self.normal);
+        rec.set_uv((alpha, beta));
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tilted(mp: Arc<dyn Material>) -> Quad {
+        // unit square leaning back from the x axis at 45 degrees
+        Quad::new(
+            Point3::zero(),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, -1.0),
+            mp,
+        )
+    }
+
+    #[test]
+    fn test_hit_and_uv() {
+        let quad = tilted(Arc::new(Lambertian::new(&Color::ones())));
+        let ray = Ray::new(Point3::new(0.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = quad.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 5.5).abs() < 1e-9);
+        assert!((rec.u - 0.25).abs() < 1e-9);
+        assert!((rec.v - 0.5).abs() < 1e-9);
+        let normal = Vec3::new(0.0, 1.0, 1.0) / 2.0_f64.sqrt();
+        assert!((rec.normal - normal).length() < 1e-9);
+        assert!(rec.front_face);
+
+        // past the far edge of the parallelogram, and parallel to it
+        let ray = Ray::new(Point3::new(2.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(quad.hit(&ray, 0.001, 100.0).is_none());
+        let ray = Ray::new(Point3::new(0.5, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(quad.hit(&ray, 0.001, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_flat_box_is_padded() {
+        let quad = Quad::new(
+            Point3::new(0.0, 2.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let bbox = quad.bounding_box(0.0, 1.0).unwrap();
+        assert!(bbox.min_p.y < 2.0 && bbox.max_p.y > 2.0);
+        assert_eq!((bbox.min_p.x, bbox.max_p.x), (0.0, 1.0));
+        let ray = Ray::new(Point3::new(0.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(bbox.hit(&ray, 0.001, 100.0));
+    }
+
+    #[test]
+    fn test_emitter() {
+        let light = Color::new(4.0, 4.0, 4.0);
+        let quad = tilted(Arc::new(DiffuseLight::new_color(&light)));
+        let ray = Ray::new(Point3::new(1.0, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = quad.hit(&ray, 0.001, 100.0).unwrap();
+        assert_eq!(rec.mat_ptr.emitted(rec.u, rec.v, &rec.p), light);
+        assert!(rec.mat_ptr.scatter(&ray, &rec).is_none());
+    }
+}
@@ -0,0 +1,400 @@
+pub use crate::object::*;
+use std::f64::consts::PI;
+
+/// Two unit vectors spanning the plane perpendicular to the unit vector `n`.
+fn orthonormal_basis(n: Vec3) -> (Vec3, Vec3) {
+    let a = if n.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let u = Vec3::cross(a, n).unit();
+    let v = Vec3::cross(n, u);
+    (u, v)
+}
+
+/// Polar texture coordinates of a point at (`x`, `z`) on a disk of `radius`.
+fn polar_uv(x: f64, z: f64, radius: f64) -> (f64, f64) {
+    let phi = z.atan2(x);
+    let u = (phi + PI) / (2.0 * PI);
+    let v = (x * x + z * z).sqrt() / radius;
+    (u, v)
+}
+
+/// Candidate hit on the cap at height `y` of a y-aligned shape, in local
+/// coordinates relative to its base center.
+fn hit_cap(orig: Vec3, dir: Vec3, y: f64, radius: f64, t_min: f64, t_max: f64) -> Option<f64> {
+    let tin = (y - orig.y) / dir.y;
+    if !(tin > t_min && tin < t_max) {
+        return None;
+    }
+    let x = orig.x + tin * dir.x;
+    let z = orig.z + tin * dir.z;
+    if x * x + z * z > radius * radius {
+        return None;
+    }
+    Some(tin)
+}
+
+/// Cylinder standing on `center` along the +y axis. Use a `Transform` to
+/// orient it differently.
+pub struct Cylinder {
+    center: Point3,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mp: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(center: Point3, radius: f64, height: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            capped: true,
+            mp,
+        }
+    }
+
+    /// Open tube without the two end caps.
+    pub fn new_uncapped(center: Point3, radius: f64, height: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            capped: false,
+            ..Self::new(center, radius, height, mp)
+        }
+    }
+}
+
+impl Object for Cylinder {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = ray.orig - self.center;
+        let d = ray.dir;
+        let mut closest = t_max;
+        // (t, outward normal, uv)
+        let mut found = None;
+
+        let a = d.x * d.x + d.z * d.z;
+        let half_b = o.x * d.x + o.z * d.z;
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        if a > 0.0 && discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            for &tin in &[(-half_b - root) / a, (-half_b + root) / a] {
+                let y = o.y + tin * d.y;
+                if tin > t_min && tin < closest && y >= 0.0 && y <= self.height {
+                    let p = o + d * tin;
+                    let outward_normal = Vec3::new(p.x, 0.0, p.z) / self.radius;
+                    let u = (p.z.atan2(p.x) + PI) / (2.0 * PI);
+                    found = Some((tin, outward_normal, (u, y / self.height)));
+                    closest = tin;
+                    break;
+                }
+            }
+        }
+
+        if self.capped {
+            for &(y, ny) in &[(0.0, -1.0), (self.height, 1.0)] {
+                if let Some(tin) = hit_cap(o, d, y, self.radius, t_min, closest) {
+                    let p = o + d * tin;
+                    let uv = polar_uv(p.x, p.z, self.radius);
+                    found = Some((tin, Vec3::new(0.0, ny, 0.0), uv));
+                    closest = tin;
+                }
+            }
+        }
+
+        let (tin, outward_normal, uv) = found?;
+        let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_uv(uv);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(AABB::new(
+            self.center - Vec3::new(self.radius, 0.0, self.radius),
+            self.center + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+/// Cone with its base disk on `center` and its apex `height` above it along
+/// the +y axis.
+pub struct Cone {
+    center: Point3,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mp: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(center: Point3, radius: f64, height: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            center,
+            radius,
+            height,
+            capped: true,
+            mp,
+        }
+    }
+
+    /// Cone without the base disk.
+    pub fn new_uncapped(center: Point3, radius: f64, height: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            capped: false,
+            ..Self::new(center, radius, height, mp)
+        }
+    }
+}
+
+impl Object for Cone {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = ray.orig - self.center;
+        let d = ray.dir;
+        let k2 = (self.radius / self.height).powi(2);
+        let w = self.height - o.y;
+        let mut closest = t_max;
+        let mut found = None;
+
+        // x^2 + z^2 = k^2 (h - y)^2
+        let a = d.x * d.x + d.z * d.z - k2 * d.y * d.y;
+        let half_b = o.x * d.x + o.z * d.z + k2 * w * d.y;
+        let c = o.x * o.x + o.z * o.z - k2 * w * w;
+        // missing roots stay at infinity and are skipped below
+        let mut roots = [std::f64::INFINITY; 2];
+        if a.abs() < 1e-12 {
+            // ray parallel to a generating line: only one crossing
+            if half_b.abs() > 1e-12 {
+                roots[0] = -c / (2.0 * half_b);
+            }
+        } else {
+            let discriminant = half_b * half_b - a * c;
+            if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                let (t0, t1) = ((-half_b - root) / a, (-half_b + root) / a);
+                roots = [t0.min(t1), t0.max(t1)];
+            }
+        }
+        for &tin in &roots {
+            let y = o.y + tin * d.y;
+            if tin > t_min && tin < closest && y >= 0.0 && y <= self.height {
+                let p = o + d * tin;
+                let normal = Vec3::new(p.x, k2 * (self.height - y), p.z);
+                // the apex has no normal of its own, so it faces up
+                let outward_normal = if normal.squared_length() > 1e-24 {
+                    normal.unit()
+                } else {
+                    Vec3::new(0.0, 1.0, 0.0)
+                };
+                let u = (p.z.atan2(p.x) + PI) / (2.0 * PI);
+                found = Some((tin, outward_normal, (u, y / self.height)));
+                closest = tin;
+                break;
+            }
+        }
+
+        if self.capped {
+            if let Some(tin) = hit_cap(o, d, 0.0, self.radius, t_min, closest) {
+                let p = o + d * tin;
+                let uv = polar_uv(p.x, p.z, self.radius);
+                found = Some((tin, Vec3::new(0.0, -1.0, 0.0), uv));
+            }
+        }
+
+        let (tin, outward_normal, uv) = found?;
+        let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_uv(uv);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(AABB::new(
+            self.center - Vec3::new(self.radius, 0.0, self.radius),
+            self.center + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+pub struct Disk {
+    center: Point3,
+    normal: Vec3,
+    radius: f64,
+    mp: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mp: Arc<dyn Material>) -> Self {
+        Self {
+            center,
+            normal: normal.unit(),
+            radius,
+            mp,
+        }
+    }
+}
+
+impl Object for Disk {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = ray.dir * self.normal;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let tin = (self.center - ray.orig) * self.normal / denom;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+
+        let offset = ray.at(tin) - self.center;
+        if offset.squared_length() > self.radius * self.radius {
+            return None;
+        }
+
+        let (tangent, bitangent) = orthonormal_basis(self.normal);
+        let mut rec = HitRecord::new(ray.at(tin), self.normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &self.normal);
+        rec.set_uv(polar_uv(offset * tangent, offset * bitangent, self.radius));
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        // half extent of a tilted disk along each axis
+        let n = self.normal;
+        let extent = Vec3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        ) * self.radius
+            + 0.0001;
+        Some(AABB::new(self.center - extent, self.center + extent))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+/// Infinite plane through `point`. It has no bounding box, so `BvhNode`
+/// keeps it outside of the tree. `u` and `v` are the (unbounded) planar
+/// coordinates relative to `point`.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    mp: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, mp: Arc<dyn Material>) -> Self {
+        Self {
+            point,
+            normal: normal.unit(),
+            mp,
+        }
+    }
+}
+
+impl Object for Plane {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = ray.dir * self.normal;
+        if denom.abs() < 1e-12 {
+            return None;
+        }
+        let tin = (self.point - ray.orig) * self.normal / denom;
+        if tin < t_min || tin > t_max {
+            return None;
+        }
+
+        let offset = ray.at(tin) - self.point;
+        let (tangent, bitangent) = orthonormal_basis(self.normal);
+        let mut rec = HitRecord::new(ray.at(tin), self.normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &self.normal);
+        rec.set_uv((offset * tangent, offset * bitangent));
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        None
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::*;
+
+    fn white() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Color::ones()))
+    }
+
+    #[test]
+    fn test_cylinder_cap_and_side() {
+        let cyl = Cylinder::new(Point3::zero(), 1.0, 2.0, white());
+        let down = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = cyl.hit(&down, 0.001, 100.0).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert_eq!(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        let open = Cylinder::new_uncapped(Point3::zero(), 1.0, 2.0, white());
+        let rec = open.hit(&down, 0.001, 100.0);
+        assert!(rec.is_none());
+
+        let side = Ray::new(Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let rec = cyl.hit(&side, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert!((rec.v - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cone_side_normal() {
+        let cone = Cone::new(Point3::zero(), 1.0, 1.0, white());
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let rec = cone.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.p.x + 0.5).abs() < 1e-9);
+        let expected = Vec3::new(-1.0, 1.0, 0.0).unit();
+        assert!((rec.normal - expected).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_cone_apex() {
+        let cone = Cone::new(Point3::zero(), 1.0, 1.0, white());
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = cone.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-9);
+        assert_eq!(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_plane_in_bvh() {
+        let mut world = HittableList::new(true);
+        world.push(Arc::new(Plane::new(
+            Point3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+            white(),
+        )));
+        world.push(Arc::new(Sphere::new(
+            Point3::new(0.0, 1.0, 0.0),
+            0.5,
+            white(),
+        )));
+        let bvh = BvhNode::new_boxed(world, 0.0, 1.0);
+        assert!(bvh.bounding_box(0.0, 1.0).is_none());
+
+        let ray = Ray::new(Point3::new(3.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = bvh.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+    }
+}
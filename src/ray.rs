@@ -5,10 +5,11 @@ pub use crate::vec3::Vec3;
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
+    pub tm: f64,
 }
 
 impl Ray {
-    pub fn new(origin: Point3, direction: Vec3) -> Self {
+    pub fn new(origin: Point3, direction: Vec3, time: f64) -> Self {
         Self {
             orig: Point3 {
                 x: origin.x,
@@ -20,6 +21,7 @@ impl Ray {
                 y: direction.y,
                 z: direction.z,
             },
+            tm: time,
         }
     }
 
@@ -0,0 +1,246 @@
+pub use crate::object::*;
+
+const MAX_STEPS: usize = 512;
+const HIT_EPSILON: f64 = 1e-6;
+const NORMAL_EPSILON: f64 = 1e-6;
+
+/// Implicit surface given by a signed distance function (negative inside),
+/// rendered by sphere tracing. The distance function must never
+/// overestimate the true distance, and the surface has to lie inside
+/// `bbox`, which is also what `BvhNode` sees.
+pub struct SdfObject {
+    sdf: Arc<dyn Fn(Point3) -> f64 + Send + Sync>,
+    bbox: AABB,
+    mp: Arc<dyn Material>,
+}
+
+impl SdfObject {
+    pub fn new<F>(sdf: F, bbox: AABB, mp: Arc<dyn Material>) -> Self
+    where
+        F: Fn(Point3) -> f64 + Send + Sync + 'static,
+    {
+        Self {
+            sdf: Arc::new(sdf),
+            bbox,
+            mp,
+        }
+    }
+
+    pub fn distance(&self, p: Point3) -> f64 {
+        (self.sdf)(p)
+    }
+
+    /// Normalized gradient of the distance field by central differences.
+    /// Where the differences cancel, as on a symmetric crease, there is no
+    /// gradient to follow and the normal faces up.
+    pub fn normal(&self, p: Point3) -> Vec3 {
+        let dx = Vec3::new(NORMAL_EPSILON, 0.0, 0.0);
+        let dy = Vec3::new(0.0, NORMAL_EPSILON, 0.0);
+        let dz = Vec3::new(0.0, 0.0, NORMAL_EPSILON);
+        let gradient = Vec3::new(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        );
+        if gradient.squared_length() < 1e-300 {
+            return Vec3::new(0.0, 1.0, 0.0);
+        }
+        gradient.unit()
+    }
+}
+
+impl Object for SdfObject {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = self.bbox.hit_range(ray, t_min, t_max)?;
+        let dir_length = ray.dir.length();
+
+        // march with |d| so that rays starting inside the surface (after a
+        // refraction) find the way out as well
+        let mut tin = t_enter;
+        for _ in 0..MAX_STEPS {
+            if tin > t_exit {
+                return None;
+            }
+            let dist = self.distance(ray.at(tin)).abs();
+            if dist < HIT_EPSILON * tin.abs().max(1.0) {
+                if tin <= t_min {
+                    return None;
+                }
+                let p = ray.at(tin);
+                let outward_normal = self.normal(p);
+                let mut rec = HitRecord::new(p, outward_normal, tin, self.mp.clone());
+                rec.set_face_normal(ray, &outward_normal);
+                rec.set_uv(get_sphere_uv(&outward_normal));
+                return Some(rec);
+            }
+            tin += dist / dir_length;
+        }
+        None
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.bbox)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+fn abs3(v: Vec3) -> Vec3 {
+    Vec3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+fn max3(v: Vec3, m: f64) -> Vec3 {
+    Vec3::new(v.x.max(m), v.y.max(m), v.z.max(m))
+}
+
+pub fn sd_sphere(p: Point3, radius: f64) -> f64 {
+    p.length() - radius
+}
+
+/// Box centered at the origin with half extents `b`.
+pub fn sd_box(p: Point3, b: Vec3) -> f64 {
+    let q = abs3(p) - b;
+    max3(q, 0.0).length() + q.x.max(q.y.max(q.z)).min(0.0)
+}
+
+/// Box with half extents `b` whose edges are rounded off by `r`.
+pub fn sd_round_box(p: Point3, b: Vec3, r: f64) -> f64 {
+    sd_box(p, b - r) - r
+}
+
+/// Torus around the y axis.
+pub fn sd_torus(p: Point3, major_radius: f64, minor_radius: f64) -> f64 {
+    let qx = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+    (qx * qx + p.y * p.y).sqrt() - minor_radius
+}
+
+/// Segment from `a` to `b` swept by a sphere of radius `r`.
+pub fn sd_capsule(p: Point3, a: Point3, b: Point3, r: f64) -> f64 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = ((pa * ba) / ba.squared_length()).max(0.0).min(1.0);
+    (pa - ba * h).length() - r
+}
+
+/// Distance estimate of the Mandelbulb fractal (power 8 gives the classic
+/// shape); it fits in a sphere of radius 1.2 around the origin.
+pub fn sd_mandelbulb(p: Point3, power: f64, iterations: usize) -> f64 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = z.length();
+
+    for _ in 0..iterations {
+        r = z.length();
+        if r > 2.0 || r <= 0.0 {
+            break;
+        }
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        let zr = r.powf(power);
+        z = Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ) * zr
+            + p;
+    }
+    if r <= 0.0 {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+pub fn op_union(d1: f64, d2: f64) -> f64 {
+    d1.min(d2)
+}
+
+pub fn op_intersection(d1: f64, d2: f64) -> f64 {
+    d1.max(d2)
+}
+
+/// `d1` with `d2` carved out of it.
+pub fn op_subtraction(d1: f64, d2: f64) -> f64 {
+    d1.max(-d2)
+}
+
+/// Union blending the two shapes over a distance of about `k`.
+pub fn op_smooth_union(d1: f64, d2: f64, k: f64) -> f64 {
+    let h = (0.5 + 0.5 * (d2 - d1) / k).max(0.0).min(1.0);
+    d2 + (d1 - d2) * h - k * h * (1.0 - h)
+}
+
+pub fn op_smooth_intersection(d1: f64, d2: f64, k: f64) -> f64 {
+    let h = (0.5 - 0.5 * (d2 - d1) / k).max(0.0).min(1.0);
+    d2 + (d1 - d2) * h + k * h * (1.0 - h)
+}
+
+pub fn op_smooth_subtraction(d1: f64, d2: f64, k: f64) -> f64 {
+    -op_smooth_union(-d1, d2, k)
+}
+
+/// Shell of thickness `thickness` around the surface.
+pub fn op_onion(d: f64, thickness: f64) -> f64 {
+    d.abs() - thickness
+}
+
+/// Infinite repetition of the cell around the origin with the given
+/// `period` along each axis; returns the point to evaluate the cell with.
+pub fn op_repeat(p: Point3, period: Vec3) -> Point3 {
+    let wrap = |x: f64, c: f64| x - c * (x / c).round();
+    Point3::new(
+        wrap(p.x, period.x),
+        wrap(p.y, period.y),
+        wrap(p.z, period.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_tracing() {
+        let bbox = AABB::new(Point3::ones() * -1.1, Point3::ones() * 1.1);
+        let ob = SdfObject::new(
+            |p| sd_sphere(p, 1.0),
+            bbox,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0), 0.0);
+        let rec = ob.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 2.0).abs() < 1e-5);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        assert!(rec.front_face);
+
+        // leaving the sphere from the inside
+        let ray = Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let rec = ob.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-5);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn test_smooth_union_bounds() {
+        let p = Point3::new(0.7, 0.2, -0.1);
+        let (d1, d2) = (sd_box(p, Vec3::ones() * 0.5), sd_torus(p, 1.0, 0.25));
+        assert!(op_smooth_union(d1, d2, 0.5) < op_union(d1, d2));
+        assert!(op_smooth_intersection(d1, d2, 0.5) > op_intersection(d1, d2));
+    }
+
+    #[test]
+    fn test_flat_crease() {
+        // a sheet of zero thickness: the differences across it cancel
+        let sheet = SdfObject::new(
+            |p| p.y.abs(),
+            AABB::new(Point3::ones() * -1.0, Point3::ones()),
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let rec = sheet.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-5);
+        assert_eq!(rec.normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+}
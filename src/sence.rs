@@ -1,6 +1,11 @@
+pub use crate::aarect::*;
 pub use crate::bvh::*;
 pub use crate::camera::*;
+use crate::gltf::*;
+use crate::medium::*;
 pub use crate::object::*;
+use crate::quad::*;
+use crate::transform::*;
 pub use crate::vec3::*;
 use rand::Rng;
 use std::sync::Arc;
@@ -8,8 +13,8 @@ use std::sync::Arc;
 pub fn init_sence(index: u32) -> (Arc<dyn Object>, Camera) {
     match index {
         1 => {
-            let world_sence = random_scene();
-            let world = BvhNode::new_boxed(world_sence, 0.0, 0.001);
+            let world_sence = random_scene(false);
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
 
             let look_from = Point3::new(13.0, 2.0, 3.0);
             let look_at = Point3::new(0.0, 0.0, 0.0);
@@ -26,13 +31,15 @@ pub fn init_sence(index: u32) -> (Arc<dyn Object>, Camera) {
                 aspect_ratio,
                 aperture,
                 dist_to_focus,
+                0.0,
+                1.0,
             );
 
             (world, cam)
         }
         2 => {
             let world_sence = light_world();
-            let world = BvhNode::new_boxed(world_sence, 0.0, 0.001);
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
 
             let look_from = Point3::new(9.0, 4.0, 4.0);
             let look_at = Point3::new(2.5, 1.0, 1.0);
@@ -49,6 +56,108 @@ pub fn init_sence(index: u32) -> (Arc<dyn Object>, Camera) {
                 aspect_ratio,
                 aperture,
                 dist_to_focus,
+                0.0,
+                1.0,
+            );
+
+            (world, cam)
+        }
+        3 => {
+            let world_sence = cornell_box();
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
+
+            let look_from = Point3::new(278.0, 278.0, -800.0);
+            let look_at = Point3::new(278.0, 278.0, 0.0);
+            let vup = Vec3::new(0.0, 1.0, 0.0);
+            let vfov = 40.0;
+            let dist_to_focus = 10.0;
+            let aperture = 0.0;
+            let aspect_ratio = 1.0;
+            let cam = Camera::new(
+                look_from,
+                look_at,
+                vup,
+                vfov,
+                aspect_ratio,
+                aperture,
+                dist_to_focus,
+                0.0,
+                1.0,
+            );
+
+            (world, cam)
+        }
+        4 => {
+            let world_sence = cornell_smoke();
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
+
+            let look_from = Point3::new(278.0, 278.0, -800.0);
+            let look_at = Point3::new(278.0, 278.0, 0.0);
+            let vup = Vec3::new(0.0, 1.0, 0.0);
+            let vfov = 40.0;
+            let dist_to_focus = 10.0;
+            let aperture = 0.0;
+            let aspect_ratio = 16.0 / 9.0;
+            let cam = Camera::new(
+                look_from,
+                look_at,
+                vup,
+                vfov,
+                aspect_ratio,
+                aperture,
+                dist_to_focus,
+                0.0,
+                1.0,
+            );
+
+            (world, cam)
+        }
+        5 => {
+            let world_sence = random_scene(true);
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
+
+            let look_from = Point3::new(13.0, 2.0, 3.0);
+            let look_at = Point3::new(0.0, 0.0, 0.0);
+            let vup = Vec3::new(0.0, 1.0, 0.0);
+            let vfov = 20.0;
+            let aspect_ratio = 16.0 / 9.0;
+            let dist_to_focus = 10.0;
+            let aperture = 0.1;
+            let cam = Camera::new(
+                look_from,
+                look_at,
+                vup,
+                vfov,
+                aspect_ratio,
+                aperture,
+                dist_to_focus,
+                0.0,
+                1.0,
+            );
+
+            (world, cam)
+        }
+        6 => {
+            let world_sence = quads();
+            let world = BvhNode::new_boxed(world_sence, 0.0, 1.0);
+
+            let look_from = Point3::new(0.0, 0.0, 9.0);
+            let look_at = Point3::new(0.0, 0.0, 0.0);
+            let vup = Vec3::new(0.0, 1.0, 0.0);
+            let vfov = 80.0;
+            let dist_to_focus = 10.0;
+            let aperture = 0.0;
+            let aspect_ratio = 16.0 / 9.0;
+            let cam = Camera::new(
+                look_from,
+                look_at,
+                vup,
+                vfov,
+                aspect_ratio,
+                aperture,
+                dist_to_focus,
+                0.0,
+                1.0,
             );
 
             (world, cam)
@@ -57,7 +166,38 @@ pub fn init_sence(index: u32) -> (Arc<dyn Object>, Camera) {
     }
 }
 
-fn random_scene() -> HittableList {
+/// World and first camera of a glTF file. Files without a camera are viewed
+/// from +z, far enough back to see the whole world.
+pub fn gltf_sence(path: &str, aspect_ratio: f64) -> Result<(Arc<dyn Object>, Camera), GltfError> {
+    let scene = load_gltf(path, aspect_ratio)?;
+    let cam = match scene.cameras.first() {
+        Some(cam) => *cam,
+        None => {
+            let bbox = scene.world.bounding_box(0.0, 1.0).unwrap();
+            let look_at = (bbox.min_p + bbox.max_p) / 2.0;
+            let radius = (bbox.max_p - bbox.min_p).length() / 2.0;
+            let vfov = 40.0;
+            let dist = radius / degrees_to_radians(vfov / 2.0).sin();
+            Camera::new(
+                look_at + Vec3::new(0.0, 0.0, dist),
+                look_at,
+                Vec3::new(0.0, 1.0, 0.0),
+                vfov,
+                aspect_ratio,
+                0.0,
+                dist,
+                0.0,
+                1.0,
+            )
+        }
+    };
+
+    Ok((Arc::new(scene.world), cam))
+}
+
+/// The final scene of the first book; with `moving` set, the diffuse
+/// spheres bounce upwards while the shutter is open.
+fn random_scene(moving: bool) -> HittableList {
     let mut world = HittableList::new(false);
 
     let checker_texture = Arc::new(CheckerTexture::new(
@@ -84,7 +224,19 @@ fn random_scene() -> HittableList {
                     //diffuse
                     let albedo = Vec3::elemul(Color::random_unit(), Color::random_unit());
                     let sphere_material = Arc::new(Lambertian::new(&albedo));
-                    world.push(Arc::new(Sphere::new(center, 0.2, sphere_material)));
+                    if moving {
+                        let center2 = center + Vec3::new(0.0, random_double_in(0.0, 0.5), 0.0);
+                        world.push(Arc::new(MovingSphere::new(
+                            center,
+                            center2,
+                            0.0,
+                            1.0,
+                            0.2,
+                            sphere_material,
+                        )));
+                    } else {
+                        world.push(Arc::new(Sphere::new(center, 0.2, sphere_material)));
+                    }
                 } else if choose_mat < 0.9 {
                     //metal
                     let albedo = Color::random(0.5, 1.0);
@@ -208,6 +360,181 @@ fn light_world() -> HittableList {
     world
 }
 
+fn cornell_box() -> HittableList {
+    let mut world = HittableList::new(true);
+
+    let red = Arc::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(&Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new_color(&Color::new(15.0, 15.0, 15.0)));
+
+    world.push(Arc::new(YZrect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.push(Arc::new(YZrect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.push(Arc::new(XZrect::new(
+        213.0, 343.0, 227.0, 332.0, 554.0, light,
+    )));
+    world.push(Arc::new(XZrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    world.push(Arc::new(XZrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    world.push(Arc::new(XYrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    let box1 = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    let box1 = Arc::new(RotateY::new(box1, 15.0));
+    world.push(Arc::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0))));
+
+    let box2 = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    let box2 = Arc::new(RotateY::new(box2, -18.0));
+    world.push(Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0))));
+
+    world
+}
+
+fn cornell_smoke() -> HittableList {
+    let mut world = HittableList::new(true);
+
+    let red = Arc::new(Lambertian::new(&Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(&Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(&Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new_color(&Color::new(7.0, 7.0, 7.0)));
+
+    world.push(Arc::new(YZrect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.push(Arc::new(YZrect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.push(Arc::new(XZrect::new(
+        113.0, 443.0, 127.0, 432.0, 554.0, light,
+    )));
+    world.push(Arc::new(XZrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        0.0,
+        white.clone(),
+    )));
+    world.push(Arc::new(XZrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+    world.push(Arc::new(XYrect::new(
+        0.0,
+        555.0,
+        0.0,
+        555.0,
+        555.0,
+        white.clone(),
+    )));
+
+    let box1 = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 330.0, 165.0),
+        white.clone(),
+    ));
+    let box1 = Arc::new(RotateY::new(box1, 15.0));
+    let box1 = Arc::new(Translate::new(box1, Vec3::new(265.0, 0.0, 295.0)));
+    world.push(Arc::new(ConstantMedium::new_color(
+        box1,
+        0.01,
+        &Color::zero(),
+    )));
+
+    let box2 = Arc::new(Cuboid::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(165.0, 165.0, 165.0),
+        white,
+    ));
+    let box2 = Arc::new(RotateY::new(box2, -18.0));
+    let box2 = Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0)));
+    world.push(Arc::new(ConstantMedium::new_color(
+        box2,
+        0.01,
+        &Color::ones(),
+    )));
+
+    world
+}
+
+/// Five colored quads facing the camera, lit by a tilted quad light.
+fn quads() -> HittableList {
+    let mut world = HittableList::new(true);
+
+    let left_red = Arc::new(Lambertian::new(&Color::new(1.0, 0.2, 0.2)));
+    let back_green = Arc::new(Lambertian::new(&Color::new(0.2, 1.0, 0.2)));
+    let right_blue = Arc::new(Lambertian::new(&Color::new(0.2, 0.2, 1.0)));
+    let upper_orange = Arc::new(Lambertian::new(&Color::new(1.0, 0.5, 0.0)));
+    let lower_teal = Arc::new(Lambertian::new(&Color::new(0.2, 0.8, 0.8)));
+    let light = Arc::new(DiffuseLight::new_color(&Color::new(6.0, 6.0, 6.0)));
+
+    world.push(Arc::new(Quad::new(
+        Point3::new(-3.0, -2.0, 5.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        left_red,
+    )));
+    world.push(Arc::new(Quad::new(
+        Point3::new(-2.0, -2.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        back_green,
+    )));
+    world.push(Arc::new(Quad::new(
+        Point3::new(3.0, -2.0, 1.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        right_blue,
+    )));
+    world.push(Arc::new(Quad::new(
+        Point3::new(-2.0, 3.0, 1.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        upper_orange,
+    )));
+    world.push(Arc::new(Quad::new(
+        Point3::new(-2.0, -3.0, 5.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        lower_teal,
+    )));
+    world.push(Arc::new(Quad::new(
+        Point3::new(-1.0, 2.5, 6.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, -0.6, 1.5),
+        light,
+    )));
+
+    world
+}
+
 fn random_double() -> f64 {
     rand::thread_rng().gen()
 }
@@ -0,0 +1,493 @@
+use crate::bvh::*;
+use crate::triangle::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SubdivisionScheme {
+    /// Loop subdivision; polygons are split into triangles first.
+    Loop,
+    /// Catmull–Clark subdivision; every level turns the mesh into quads.
+    CatmullClark,
+}
+
+type EdgeKey = (u32, u32);
+
+fn edge_key(a: u32, b: u32) -> EdgeKey {
+    (a.min(b), a.max(b))
+}
+
+/// Polygon mesh with a set of edges marked as infinitely sharp creases.
+struct PolyMesh {
+    positions: Vec<Point3>,
+    faces: Vec<Vec<u32>>,
+    creases: HashSet<EdgeKey>,
+}
+
+/// Adjacency of a `PolyMesh`, as the subdivision rules need it.
+struct Topology {
+    /// Faces on each edge.
+    edge_faces: HashMap<EdgeKey, Vec<usize>>,
+    neighbors: Vec<Vec<u32>>,
+    vertex_faces: Vec<Vec<usize>>,
+}
+
+impl PolyMesh {
+    fn edges(face: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (0..face.len()).map(move |i| (face[i], face[(i + 1) % face.len()]))
+    }
+
+    fn topology(&self) -> Topology {
+        let mut edge_faces = HashMap::new() as HashMap<EdgeKey, Vec<usize>>;
+        let mut neighbors = vec![vec![]; self.positions.len()];
+        let mut vertex_faces = vec![vec![]; self.positions.len()];
+        for (f, face) in self.faces.iter().enumerate() {
+            for (a, b) in Self::edges(face) {
+                let faces = edge_faces.entry(edge_key(a, b)).or_default();
+                if faces.is_empty() {
+                    neighbors[a as usize].push(b);
+                    neighbors[b as usize].push(a);
+                }
+                faces.push(f);
+            }
+            for &v in face {
+                vertex_faces[v as usize].push(f);
+            }
+        }
+        Topology {
+            edge_faces,
+            neighbors,
+            vertex_faces,
+        }
+    }
+
+    /// Creases, boundaries and non-manifold edges all stay sharp.
+    fn is_sharp(&self, topo: &Topology, key: EdgeKey) -> bool {
+        self.creases.contains(&key) || topo.edge_faces[&key].len() != 2
+    }
+
+    /// Neighbors of `v` across sharp edges, or `None` for a corner that has
+    /// to stay where it is.
+    fn sharp_neighbors(&self, topo: &Topology, v: u32) -> Option<Vec<u32>> {
+        let sharp: Vec<u32> = topo.neighbors[v as usize]
+            .iter()
+            .copied()
+            .filter(|&n| self.is_sharp(topo, edge_key(v, n)))
+            .collect();
+        if sharp.len() > 2 || (sharp.len() == 2 && topo.vertex_faces[v as usize].len() == 1) {
+            None
+        } else {
+            Some(sharp)
+        }
+    }
+
+    /// Point for vertex `v` on its crease or boundary curve, if it is on
+    /// one: the cubic B-spline rule along the two sharp edges.
+    fn crease_vertex(&self, topo: &Topology, v: u32) -> Option<Point3> {
+        let p = self.positions[v as usize];
+        match self.sharp_neighbors(topo, v) {
+            None => Some(p),
+            Some(sharp) if sharp.len() == 2 => Some(
+                p * 0.75
+                    + (self.positions[sharp[0] as usize] + self.positions[sharp[1] as usize])
+                        * 0.125,
+            ),
+            Some(_) => None,
+        }
+    }
+
+    fn fan_triangulated(self) -> Self {
+        let faces = self
+            .faces
+            .iter()
+            .flat_map(|face| (1..face.len() - 1).map(move |i| vec![face[0], face[i], face[i + 1]]))
+            .collect();
+        Self { faces, ..self }
+    }
+
+    /// One level of Loop subdivision of a triangle mesh.
+    fn loop_step(&self) -> Self {
+        let topo = self.topology();
+        let mut positions: Vec<Point3> = (0..self.positions.len() as u32)
+            .map(|v| {
+                self.crease_vertex(&topo, v).unwrap_or_else(|| {
+                    let ring = &topo.neighbors[v as usize];
+                    let n = ring.len() as f64;
+                    let beta = if ring.len() == 3 {
+                        3.0 / 16.0
+                    } else {
+                        3.0 / (8.0 * n)
+                    };
+                    let sum = ring
+                        .iter()
+                        .fold(Vec3::zero(), |acc, &u| acc + self.positions[u as usize]);
+                    self.positions[v as usize] * (1.0 - n * beta) + sum * beta
+                })
+            })
+            .collect();
+
+        let mut edge_points = HashMap::new();
+        for (&key, faces) in &topo.edge_faces {
+            let (a, b) = (
+                self.positions[key.0 as usize],
+                self.positions[key.1 as usize],
+            );
+            let p = if self.is_sharp(&topo, key) {
+                (a + b) * 0.5
+            } else {
+                let opposite = |f: usize| {
+                    let face = &self.faces[f];
+                    let v = *face.iter().find(|&&v| v != key.0 && v != key.1).unwrap();
+                    self.positions[v as usize]
+                };
+                (a + b) * 0.375 + (opposite(faces[0]) + opposite(faces[1])) * 0.125
+            };
+            edge_points.insert(key, positions.len() as u32);
+            positions.push(p);
+        }
+
+        let mut faces = vec![];
+        for face in &self.faces {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let (ab, bc, ca) = (
+                edge_points[&edge_key(a, b)],
+                edge_points[&edge_key(b, c)],
+                edge_points[&edge_key(c, a)],
+            );
+            faces.push(vec![a, ab, ca]);
+            faces.push(vec![b, bc, ab]);
+            faces.push(vec![c, ca, bc]);
+            faces.push(vec![ab, bc, ca]);
+        }
+
+        Self {
+            positions,
+            faces,
+            creases: self.split_creases(&edge_points),
+        }
+    }
+
+    /// One level of Catmull–Clark subdivision of a polygon mesh.
+    fn catmull_clark_step(&self) -> Self {
+        let topo = self.topology();
+        let face_points: Vec<Point3> = self
+            .faces
+            .iter()
+            .map(|face| {
+                face.iter()
+                    .fold(Vec3::zero(), |acc, &v| acc + self.positions[v as usize])
+                    / face.len() as f64
+            })
+            .collect();
+
+        let mut positions: Vec<Point3> = (0..self.positions.len() as u32)
+            .map(|v| {
+                self.crease_vertex(&topo, v).unwrap_or_else(|| {
+                    let p = self.positions[v as usize];
+                    let incident = &topo.vertex_faces[v as usize];
+                    let ring = &topo.neighbors[v as usize];
+                    let n = ring.len() as f64;
+                    let q = incident
+                        .iter()
+                        .fold(Vec3::zero(), |acc, &f| acc + face_points[f])
+                        / incident.len() as f64;
+                    let r = ring.iter().fold(Vec3::zero(), |acc, &u| {
+                        acc + (p + self.positions[u as usize]) * 0.5
+                    }) / n;
+                    (q + r * 2.0 + p * (n - 3.0)) / n
+                })
+            })
+            .collect();
+
+        let mut edge_points = HashMap::new();
+        for (&key, faces) in &topo.edge_faces {
+            let (a, b) = (
+                self.positions[key.0 as usize],
+                self.positions[key.1 as usize],
+            );
+            let p = if self.is_sharp(&topo, key) {
+                (a + b) * 0.5
+            } else {
+                (a + b + face_points[faces[0]] + face_points[faces[1]]) * 0.25
+            };
+            edge_points.insert(key, positions.len() as u32);
+            positions.push(p);
+        }
+
+        let mut faces = vec![];
+        for (f, face) in self.faces.iter().enumerate() {
+            let center = positions.len() as u32;
+            positions.push(face_points[f]);
+            let n = face.len();
+            for i in 0..n {
+                let (prev, cur, next) = (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+                faces.push(vec![
+                    cur,
+                    edge_points[&edge_key(cur, next)],
+                    center,
+                    edge_points[&edge_key(prev, cur)],
+                ]);
+            }
+        }
+
+        Self {
+            positions,
+            faces,
+            creases: self.split_creases(&edge_points),
+        }
+    }
+
+    /// Both halves of every split crease edge stay creased.
+    fn split_creases(&self, edge_points: &HashMap<EdgeKey, u32>) -> HashSet<EdgeKey> {
+        let mut res = HashSet::new();
+        for &(a, b) in &self.creases {
+            if let Some(&mid) = edge_points.get(&(a, b)) {
+                res.insert(edge_key(a, mid));
+                res.insert(edge_key(mid, b));
+            }
+        }
+        res
+    }
+
+    /// Triangles with vertex normals averaged over the faces around each
+    /// vertex, but not across sharp edges, so creases keep a hard edge.
+    fn into_triangle_mesh(self, mat_ptr: Arc<dyn Material>) -> TriangleMesh {
+        let topo = self.topology();
+
+        // one slot per face corner; corners joined by a smooth edge share a normal
+        let mut offsets = vec![0; self.faces.len() + 1];
+        for (f, face) in self.faces.iter().enumerate() {
+            offsets[f + 1] = offsets[f] + face.len();
+        }
+        let corner =
+            |f: usize, v: u32| offsets[f] + self.faces[f].iter().position(|&u| u == v).unwrap();
+        let mut parent: Vec<usize> = (0..offsets[self.faces.len()]).collect();
+        fn find(parent: &mut [usize], mut x: usize) -> usize {
+            while parent[x] != x {
+                parent[x] = parent[parent[x]];
+                x = parent[x];
+            }
+            x
+        }
+        for (&key, faces) in &topo.edge_faces {
+            if self.is_sharp(&topo, key) {
+                continue;
+            }
+            for &v in &[key.0, key.1] {
+                let (x, y) = (
+                    find(&mut parent, corner(faces[0], v)),
+                    find(&mut parent, corner(faces[1], v)),
+                );
+                parent[x] = y;
+            }
+        }
+
+        let mut slot_of_root = HashMap::new();
+        let mut positions = vec![];
+        let mut normals = vec![] as Vec<Vec3>;
+        let mut faces = vec![];
+        for (f, face) in self.faces.iter().enumerate() {
+            // Newell's method: area weighted and fine for non-planar quads
+            let n = face.len();
+            let face_normal = (0..n).fold(Vec3::zero(), |acc, i| {
+                let (a, b) = (
+                    self.positions[face[i] as usize],
+                    self.positions[face[(i + 1) % n] as usize],
+                );
+                acc + Vec3::cross(a, b)
+            });
+
+            let mut slots = vec![];
+            for (k, &v) in face.iter().enumerate() {
+                let root = find(&mut parent, offsets[f] + k);
+                let slot = *slot_of_root.entry(root).or_insert_with(|| {
+                    positions.push(self.positions[v as usize]);
+                    normals.push(Vec3::zero());
+                    positions.len() as u32 - 1
+                });
+                normals[slot as usize] += face_normal;
+                slots.push(slot);
+            }
+            for i in 1..n - 1 {
+                faces.push([slots[0], slots[i], slots[i + 1]]);
+            }
+        }
+        let normals = normals
+            .into_iter()
+            .map(|n| {
+                if n.squared_length() > 0.0 {
+                    n.unit()
+                } else {
+                    n
+                }
+            })
+            .collect();
+
+        TriangleMesh {
+            positions,
+            normals: Some(normals),
+            uvs: None,
+            colors: None,
+            faces,
+            mat_ptr,
+        }
+    }
+}
+
+/// Smooth surface refined from a coarse control mesh. The mesh is
+/// subdivided `levels` times up front and the resulting triangles go into
+/// a BVH; boundaries and the edges listed as creases stay sharp.
+pub struct SubdivisionMesh {
+    tree: Arc<dyn Object>,
+}
+
+impl SubdivisionMesh {
+    /// `faces` are polygons of indices into `positions`, and `creases` are
+    /// pairs of vertices joined by an edge of the control mesh.
+    pub fn new(
+        positions: Vec<Point3>,
+        faces: Vec<Vec<u32>>,
+        creases: &[[u32; 2]],
+        scheme: SubdivisionScheme,
+        levels: u32,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        for face in &faces {
+            assert!(face.len() >= 3, "faces need at least 3 vertices");
+            assert!(
+                face.iter().all(|&v| (v as usize) < positions.len()),
+                "face refers to a missing vertex"
+            );
+        }
+
+        let mut mesh = PolyMesh {
+            positions,
+            faces,
+            creases: creases.iter().map(|e| edge_key(e[0], e[1])).collect(),
+        };
+        if scheme == SubdivisionScheme::Loop {
+            mesh = mesh.fan_triangulated();
+        }
+        for _ in 0..levels {
+            mesh = match scheme {
+                SubdivisionScheme::Loop => mesh.loop_step(),
+                SubdivisionScheme::CatmullClark => mesh.catmull_clark_step(),
+            };
+        }
+
+        let triangles = mesh.into_triangle_mesh(mat_ptr).into_triangles();
+        let tree = if triangles.is_empty() {
+            Arc::new(HittableList::default()) as Arc<dyn Object>
+        } else {
+            BvhNode::init(triangles, 0.0, 1.0, true)
+        };
+        Self { tree }
+    }
+}
+
+impl Object for SubdivisionMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.tree.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.tree.bounding_box(t0, t1)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> (Vec<Point3>, Vec<Vec<u32>>) {
+        let positions = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { -1.0 } else { 1.0 },
+                    if i & 2 == 0 { -1.0 } else { 1.0 },
+                    if i & 4 == 0 { -1.0 } else { 1.0 },
+                )
+            })
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        (positions, faces)
+    }
+
+    fn mat() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(&Color::ones()))
+    }
+
+    fn along_x() -> Ray {
+        Ray::new(Point3::new(10.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0)
+    }
+
+    #[test]
+    fn test_catmull_clark_cube() {
+        let (positions, faces) = cube();
+        let smooth = SubdivisionMesh::new(
+            positions.clone(),
+            faces.clone(),
+            &[],
+            SubdivisionScheme::CatmullClark,
+            3,
+            mat(),
+        );
+        let rec = smooth.hit(&along_x(), 0.001, 100.0).unwrap();
+        assert!(rec.p.x > 0.5 && rec.p.x < 0.9);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+
+        // creasing every edge keeps the cube as it is
+        let edges: Vec<[u32; 2]> = faces
+            .iter()
+            .flat_map(|f| PolyMesh::edges(f).map(|(a, b)| [a, b]).collect::<Vec<_>>())
+            .collect();
+        let creased = SubdivisionMesh::new(
+            positions,
+            faces,
+            &edges,
+            SubdivisionScheme::CatmullClark,
+            2,
+            mat(),
+        );
+        let ray = Ray::new(Point3::new(10.0, 0.9, -0.9), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let rec = creased.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.p.x - 1.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_loop_boundary() {
+        // a flat square made of two triangles stays flat, and its corners with
+        // a single face stay put
+        let positions = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let square = SubdivisionMesh::new(
+            positions,
+            vec![vec![0, 1, 2, 3]],
+            &[],
+            SubdivisionScheme::Loop,
+            2,
+            mat(),
+        );
+        let bbox = square.bounding_box(0.0, 1.0).unwrap();
+        assert!(bbox.max_p.x > 0.9999 && bbox.min_p.y < 0.0001);
+        let ray = Ray::new(Point3::new(0.99, 0.02, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = square.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+    }
+}
@@ -0,0 +1,578 @@
+use crate::bvh::*;
+use crate::triangle::*;
+use rusttype::{point, Font, OutlineBuilder, Scale};
+use std::fmt;
+
+type Point2 = (f64, f64);
+
+#[derive(Debug)]
+pub enum Text3DError {
+    /// The hole at this index among the contours could not be joined to the
+    /// contour around it, typically because the two overlap.
+    UnbridgedHole(usize),
+}
+
+impl fmt::Display for Text3DError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Text3DError::UnbridgedHole(index) => {
+                write!(f, "contour {} is not a hole inside another contour", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Text3DError {}
+
+/// Collects glyph outlines as closed polygons, flattening the quadratic
+/// and cubic segments to within `tolerance`.
+struct ContourBuilder {
+    contours: Vec<Vec<Point2>>,
+    current: Vec<Point2>,
+    tolerance: f64,
+    /// Position of the glyph being outlined.
+    origin: Point2,
+    /// Last point passed in, in the builder's y-down coordinates.
+    pen: Point2,
+}
+
+impl ContourBuilder {
+    fn push(&mut self, p: Point2) {
+        self.pen = p;
+        // outlines come in y-down, flip them to y-up
+        let p = (self.origin.0 + p.0, -(self.origin.1 + p.1));
+        if self.current.last() != Some(&p) {
+            self.current.push(p);
+        }
+    }
+
+    fn segments(&self, deviation: f64) -> usize {
+        ((deviation / self.tolerance).sqrt().ceil() as usize)
+            .max(1)
+            .min(64)
+    }
+
+    fn finish(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+impl OutlineBuilder for ContourBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish();
+        self.push((x as f64, y as f64));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push((x as f64, y as f64));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.pen;
+        let (x1, y1, x, y) = (x1 as f64, y1 as f64, x as f64, y as f64);
+        let deviation = ((x0 - 2.0 * x1 + x).powi(2) + (y0 - 2.0 * y1 + y).powi(2)).sqrt() / 4.0;
+        let n = self.segments(deviation);
+        for i in 1..=n {
+            let t = i as f64 / n as f64;
+            let s = 1.0 - t;
+            self.push((
+                s * s * x0 + 2.0 * s * t * x1 + t * t * x,
+                s * s * y0 + 2.0 * s * t * y1 + t * t * y,
+            ));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.pen;
+        let (x1, y1, x2, y2, x, y) = (
+            x1 as f64, y1 as f64, x2 as f64, y2 as f64, x as f64, y as f64,
+        );
+        let d1 = ((x0 - 2.0 * x1 + x2).powi(2) + (y0 - 2.0 * y1 + y2).powi(2)).sqrt();
+        let d2 = ((x1 - 2.0 * x2 + x).powi(2) + (y1 - 2.0 * y2 + y).powi(2)).sqrt();
+        let n = self.segments(0.75 * d1.max(d2));
+        for i in 1..=n {
+            let t = i as f64 / n as f64;
+            let s = 1.0 - t;
+            let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+            self.push((
+                a * x0 + b * x1 + c * x2 + d * x,
+                a * y0 + b * y1 + c * y2 + d * y,
+            ));
+        }
+    }
+
+    fn close(&mut self) {
+        self.finish();
+    }
+}
+
+fn signed_area(points: &[Point2], contour: &[usize]) -> f64 {
+    let n = contour.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (points[contour[i]], points[contour[(i + 1) % n]]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Even-odd point in polygon test.
+fn contains(points: &[Point2], contour: &[usize], p: Point2) -> bool {
+    let n = contour.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (a, b) = (points[contour[i]], points[contour[(i + 1) % n]]);
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn cross(o: Point2, a: Point2, b: Point2) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn in_triangle(p: Point2, a: Point2, b: Point2, c: Point2) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+/// Splices `hole` into `outer` through a bridge from the hole's rightmost
+/// vertex to a vertex of `outer` it can see, giving one polygon that walks
+/// around both. Returns `false` if the hole is not inside `outer`.
+fn bridge_hole(points: &[Point2], outer: &mut Vec<usize>, hole: &[usize]) -> bool {
+    let (hi, &m_idx) = hole
+        .iter()
+        .enumerate()
+        .max_by(|a, b| points[*a.1].0.partial_cmp(&points[*b.1].0).unwrap())
+        .unwrap();
+    let m = points[m_idx];
+
+    // nearest edge crossed by a ray from m towards +x
+    let n = outer.len();
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (points[outer[i]], points[outer[(i + 1) % n]]);
+        if (a.1 > m.1) == (b.1 > m.1) || (a.1 - b.1).abs() < 1e-12 {
+            continue;
+        }
+        let x = a.0 + (m.1 - a.1) / (b.1 - a.1) * (b.0 - a.0);
+        if x >= m.0 && best.map_or(true, |(bx, _)| x < bx) {
+            let pick = if a.0 > b.0 { i } else { (i + 1) % n };
+            best = Some((x, pick));
+        }
+    }
+    let (ix, mut pick) = match best {
+        Some(best) => best,
+        None => return false,
+    };
+
+    // another vertex may block the view; then take the one inside the
+    // triangle (m, hit, candidate) closest in angle to the ray
+    let i_point = (ix, m.1);
+    let p = points[outer[pick]];
+    let (ta, tb) = if p.1 < m.1 {
+        (p, i_point)
+    } else {
+        (i_point, p)
+    };
+    let mut best_angle = std::f64::INFINITY;
+    for (k, &v_idx) in outer.iter().enumerate() {
+        let v = points[v_idx];
+        if k == pick || v == m || !in_triangle(v, m, ta, tb) {
+            continue;
+        }
+        let angle = (v.1 - m.1).abs().atan2(v.0 - m.0);
+        if angle < best_angle {
+            best_angle = angle;
+            pick = k;
+        }
+    }
+
+    let mut merged = outer[..=pick].to_vec();
+    merged.extend(hole[hi..].iter().chain(&hole[..=hi]));
+    merged.extend(&outer[pick..]);
+    *outer = merged;
+    true
+}
+
+/// Ear clipping of a counter-clockwise polygon, which may touch itself
+/// along the bridges made by `bridge_hole`.
+fn triangulate(points: &[Point2], polygon: &[usize]) -> Vec<[usize; 3]> {
+    let mut idx = polygon.to_vec();
+    let mut res = vec![];
+    while idx.len() > 3 {
+        let n = idx.len();
+        let corner = |i: usize| (idx[(i + n - 1) % n], idx[i], idx[(i + 1) % n]);
+        let is_ear = |i: usize| {
+            let (a, b, c) = corner(i);
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            cross(pa, pb, pc) > 1e-12
+                && idx.iter().all(|&v| {
+                    let pv = points[v];
+                    pv == pa || pv == pb || pv == pc || !in_triangle(pv, pa, pb, pc)
+                })
+        };
+
+        match (0..n).find(|&i| is_ear(i)) {
+            Some(i) => {
+                let (a, b, c) = corner(i);
+                res.push([a, b, c]);
+                idx.remove(i);
+            }
+            None => {
+                // only degenerate corners left: drop a flat one if there is
+                // one, or give up on the rest of a broken outline
+                match (0..n).find(|&i| {
+                    let (a, b, c) = corner(i);
+                    cross(points[a], points[b], points[c]).abs() <= 1e-12
+                }) {
+                    Some(i) => {
+                        idx.remove(i);
+                    }
+                    None => return res,
+                }
+            }
+        }
+    }
+    if idx.len() == 3 && cross(points[idx[0]], points[idx[1]], points[idx[2]]) > 1e-12 {
+        res.push([idx[0], idx[1], idx[2]]);
+    }
+    res
+}
+
+/// Text and logo geometry: closed 2D outlines turned into a solid with
+/// flat front and back faces at z = 0 and z = -`depth`. A non-zero `bevel`
+/// chamfers both rims, insetting the faces by that much.
+pub struct Text3D {
+    tree: Arc<dyn Object>,
+}
+
+impl Text3D {
+    /// `text` set in `font` along +x from the origin, with the baseline on
+    /// the x axis and the font scaled to a line height of `size`. Glyph
+    /// contours must not overlap each other, which holds for most static
+    /// fonts but not for many variable ones; a hole crossing its outline is
+    /// reported as an error.
+    pub fn new(
+        font: &Font,
+        text: &str,
+        size: f64,
+        depth: f64,
+        bevel: f64,
+        mat: Arc<dyn Material>,
+    ) -> Result<Self, Text3DError> {
+        let mut builder = ContourBuilder {
+            contours: vec![],
+            current: vec![],
+            tolerance: size * 0.001,
+            origin: (0.0, 0.0),
+            pen: (0.0, 0.0),
+        };
+        for glyph in font.layout(text, Scale::uniform(size as f32), point(0.0, 0.0)) {
+            let position = glyph.position();
+            builder.origin = (position.x as f64, position.y as f64);
+            glyph.unpositioned().build_outline(&mut builder);
+            builder.finish();
+        }
+        Self::from_contours(builder.contours, depth, bevel, mat)
+    }
+
+    /// Solid from closed polygons in the xy plane, in any orientation.
+    /// Contours nested inside an odd number of others are holes; one that
+    /// crosses the contour around it is an error.
+    pub fn from_contours(
+        contours: Vec<Vec<Point2>>,
+        depth: f64,
+        bevel: f64,
+        mat: Arc<dyn Material>,
+    ) -> Result<Self, Text3DError> {
+        let bevel = bevel.max(0.0).min(depth * 0.49);
+        let mut points = vec![] as Vec<Point2>;
+        let mut loops = vec![] as Vec<Vec<usize>>;
+        for contour in contours {
+            let start = points.len();
+            points.extend(contour);
+            loops.push((start..points.len()).collect());
+        }
+
+        // classify by nesting, then orient outers CCW and holes CW so that
+        // the solid is always on the left of an edge
+        let nesting: Vec<usize> = loops
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let p = points[l[0]];
+                (0..loops.len())
+                    .filter(|&j| j != i && contains(&points, &loops[j], p))
+                    .count()
+            })
+            .collect();
+        for (l, depth) in loops.iter_mut().zip(&nesting) {
+            let ccw = signed_area(&points, l) > 0.0;
+            if ccw == (depth % 2 == 1) {
+                l.reverse();
+            }
+        }
+
+        let mut faces = vec![];
+        for (i, outer) in loops.iter().enumerate() {
+            if nesting[i] % 2 == 1 {
+                continue;
+            }
+            let mut holes: Vec<usize> = (0..loops.len())
+                .filter(|&j| {
+                    nesting[j] == nesting[i] + 1 && contains(&points, outer, points[loops[j][0]])
+                })
+                .collect();
+            holes.sort_by(|a, b| {
+                let max_x = |l: &Vec<usize>| {
+                    l.iter()
+                        .map(|&v| points[v].0)
+                        .fold(-std::f64::INFINITY, f64::max)
+                };
+                max_x(&loops[*b]).partial_cmp(&max_x(&loops[*a])).unwrap()
+            });
+
+            let mut polygon = outer.clone();
+            for hole in holes {
+                if !bridge_hole(&points, &mut polygon, &loops[hole]) {
+                    return Err(Text3DError::UnbridgedHole(hole));
+                }
+            }
+            faces.extend(triangulate(&points, &polygon));
+        }
+
+        let triangles = extrude(&points, &loops, &faces, depth, bevel, &mat);
+        let tree = if triangles.is_empty() {
+            Arc::new(HittableList::default()) as Arc<dyn Object>
+        } else {
+            BvhNode::init(triangles, 0.0, 1.0, true)
+        };
+        Ok(Self { tree })
+    }
+}
+
+/// Caps, side walls and bevel bands of the solid. `loops` must already be
+/// oriented with the solid on their left.
+fn extrude(
+    points: &[Point2],
+    loops: &[Vec<usize>],
+    faces: &[[usize; 3]],
+    depth: f64,
+    bevel: f64,
+    mat: &Arc<dyn Material>,
+) -> Vec<Arc<dyn Object>> {
+    let at = |p: Point2, z: f64| Point3::new(p.0, p.1, z);
+    let left_normal = |a: Point2, b: Point2| {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = (dx * dx + dy * dy).sqrt().max(1e-300);
+        (-dy / len, dx / len)
+    };
+
+    // inset every vertex by `bevel` along the mitered normal of its edges
+    let mut inset = points.to_vec();
+    for l in loops {
+        let n = l.len();
+        for i in 0..n {
+            let (prev, cur, next) = (
+                points[l[(i + n - 1) % n]],
+                points[l[i]],
+                points[l[(i + 1) % n]],
+            );
+            let (n1, n2) = (left_normal(prev, cur), left_normal(cur, next));
+            let m = (n1.0 + n2.0, n1.1 + n2.1);
+            let len = (m.0 * m.0 + m.1 * m.1).sqrt();
+            if len < 1e-9 {
+                continue;
+            }
+            let m = (m.0 / len, m.1 / len);
+            let scale = bevel / (m.0 * n1.0 + m.1 * n1.1).max(0.25);
+            inset[l[i]] = (cur.0 + m.0 * scale, cur.1 + m.1 * scale);
+        }
+    }
+
+    let mut res = vec![] as Vec<Arc<dyn Object>>;
+    let mut push = |v: [Point3; 3], normals: Option<[Vec3; 3]>| {
+        res.push(Arc::new(Triangle::new_with_attributes(
+            v,
+            normals,
+            None,
+            mat.clone(),
+        )));
+    };
+
+    for &[a, b, c] in faces {
+        push(
+            [at(inset[a], 0.0), at(inset[b], 0.0), at(inset[c], 0.0)],
+            None,
+        );
+        push(
+            [
+                at(inset[a], -depth),
+                at(inset[c], -depth),
+                at(inset[b], -depth),
+            ],
+            None,
+        );
+    }
+
+    let (z_front, z_back) = (-bevel, -depth + bevel);
+    let smooth_cos = (30.0f64).to_radians().cos();
+    for l in loops {
+        let n = l.len();
+        let outward: Vec<Vec3> = (0..n)
+            .map(|i| {
+                let (nx, ny) = left_normal(points[l[i]], points[l[(i + 1) % n]]);
+                Vec3::new(-nx, -ny, 0.0)
+            })
+            .collect();
+        // curves get smooth walls, corners stay sharp
+        let vertex_normal = |i: usize, edge: usize| {
+            let (before, after) = (outward[(i + n - 1) % n], outward[i % n]);
+            if before * after > smooth_cos {
+                (before + after).unit()
+            } else {
+                outward[edge]
+            }
+        };
+
+        for i in 0..n {
+            let j = (i + 1) % n;
+            let (p, q) = (points[l[i]], points[l[j]]);
+            let (np, nq) = (vertex_normal(i, i), vertex_normal(j, i));
+            push(
+                [at(p, z_front), at(p, z_back), at(q, z_front)],
+                Some([np, np, nq]),
+            );
+            push(
+                [at(q, z_front), at(p, z_back), at(q, z_back)],
+                Some([nq, np, nq]),
+            );
+
+            if bevel > 0.0 {
+                let (ip, iq) = (inset[l[i]], inset[l[j]]);
+                push([at(p, z_front), at(q, z_front), at(iq, 0.0)], None);
+                push([at(p, z_front), at(iq, 0.0), at(ip, 0.0)], None);
+                push([at(p, z_back), at(iq, -depth), at(q, z_back)], None);
+                push([at(p, z_back), at(ip, -depth), at(iq, -depth)], None);
+            }
+        }
+    }
+    res
+}
+
+impl Object for Text3D {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.tree.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.tree.bounding_box(t0, t1)
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f64, y0: f64, side: f64) -> Vec<Point2> {
+        vec![
+            (x0, y0),
+            (x0, y0 + side),
+            (x0 + side, y0 + side),
+            (x0 + side, y0),
+        ]
+    }
+
+    #[test]
+    fn test_triangulate_with_hole() {
+        let points: Vec<Point2> = square(0.0, 0.0, 4.0)
+            .into_iter()
+            .rev()
+            .chain(square(1.0, 1.0, 2.0))
+            .collect();
+        let mut polygon = vec![0, 1, 2, 3];
+        assert!(bridge_hole(&points, &mut polygon, &[4, 5, 6, 7]));
+        let area: f64 = triangulate(&points, &polygon)
+            .iter()
+            .map(|t| cross(points[t[0]], points[t[1]], points[t[2]]) / 2.0)
+            .sum();
+        assert!((area - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_overlapping_hole() {
+        // the inner square starts inside the outer one but sticks out of it
+        let res = Text3D::from_contours(
+            vec![square(0.0, 0.0, 4.0), square(1.0, 1.0, 5.0)],
+            1.0,
+            0.0,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        assert!(matches!(res, Err(Text3DError::UnbridgedHole(1))));
+    }
+
+    #[test]
+    fn test_extruded_frame() {
+        let frame = Text3D::from_contours(
+            vec![square(0.0, 0.0, 4.0), square(1.0, 1.0, 2.0)],
+            1.0,
+            0.1,
+            Arc::new(Lambertian::new(&Color::ones())),
+        )
+        .unwrap();
+        let down = Vec3::new(0.0, 0.0, -1.0);
+
+        // the front face is inset by the bevel, which slopes down outside it
+        let rec = frame
+            .hit(
+                &Ray::new(Point3::new(0.5, 0.5, 5.0), down, 0.0),
+                0.001,
+                100.0,
+            )
+            .unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-9);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+        let rec = frame
+            .hit(
+                &Ray::new(Point3::new(0.05, 2.0, 5.0), down, 0.0),
+                0.001,
+                100.0,
+            )
+            .unwrap();
+        assert!((rec.t - 5.05).abs() < 1e-9);
+
+        // through the hole
+        assert!(frame
+            .hit(
+                &Ray::new(Point3::new(2.0, 2.0, 5.0), down, 0.0),
+                0.001,
+                100.0
+            )
+            .is_none());
+
+        // side wall facing +x
+        let rec = frame
+            .hit(
+                &Ray::new(Point3::new(10.0, 2.0, -0.5), Vec3::new(-1.0, 0.0, 0.0), 0.0),
+                0.001,
+                100.0,
+            )
+            .unwrap();
+        assert!((rec.t - 6.0).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+    }
+}
@@ -1,8 +1,15 @@
+use crate::object::HitRecord;
 use crate::vec3::*;
 use std::sync::Arc;
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+
+    /// Texture lookup with the whole hit record at hand, for textures that
+    /// need more than (`u`, `v`, `p`), such as vertex colors.
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, &rec.p)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -67,3 +74,68 @@ impl Texture for CheckerTexture {
         }
     }
 }
+
+/// Color interpolated from the vertices of the hit geometry (see
+/// `HitRecord::color`); `fallback` is used where there is none.
+#[derive(Clone)]
+pub struct VertexColorTexture {
+    fallback: Arc<dyn Texture>,
+}
+
+impl VertexColorTexture {
+    pub fn new(fallback: Arc<dyn Texture>) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.fallback.value(u, v, p)
+    }
+
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        match rec.color {
+            Some(c) => c,
+            None => self.fallback.value_at(rec),
+        }
+    }
+}
+
+/// Texture sampled from an RGB image, with (0, 0) at the bottom-left
+/// corner. Coordinates outside [0, 1] wrap around, so the image repeats.
+#[derive(Clone)]
+pub struct ImageTexture {
+    image: image::RgbImage,
+}
+
+impl ImageTexture {
+    pub fn new<P: AsRef<std::path::Path>>(path: P) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(image::open(path)?))
+    }
+
+    pub fn from_image(image: image::DynamicImage) -> Self {
+        Self {
+            image: image.to_rgb(),
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+        let u = u - u.floor();
+        let v = 1.0 - (v - v.floor());
+
+        let i = ((u * width as f64) as u32).min(width - 1);
+        let j = ((v * height as f64) as u32).min(height - 1);
+        let pixel = self.image.get_pixel(i, j);
+        Color::new(
+            pixel[0] as f64 / 255.0,
+            pixel[1] as f64 / 255.0,
+            pixel[2] as f64 / 255.0,
+        )
+    }
+}
@@ -0,0 +1,124 @@
+use crate::bvh::*;
+use crate::instance::*;
+use crate::linear_bvh::*;
+use crate::transform::*;
+
+/// Bottom-level structure for a `Tlas`: a BVH over `objects` in their own
+/// object space, to be shared by any number of `Instance`s.
+pub fn build_blas(objects: Vec<Arc<dyn Object>>) -> Arc<dyn Object> {
+    Arc::new(LinearBvh::new(objects, 0.0, 1.0, true, SplitMethod::Sah))
+}
+
+/// Two-level acceleration structure: a top-level BVH over instances, each
+/// pointing at a bottom-level BVH (see `build_blas`) that is built once in
+/// object space. Moving instances only means rebuilding the top level,
+/// whose size is the number of instances rather than of primitives.
+pub struct Tlas {
+    instances: Vec<Arc<Instance>>,
+    top: LinearBvh,
+    if_dark: bool,
+}
+
+impl Tlas {
+    pub fn new(instances: Vec<Instance>, if_dark: bool) -> Self {
+        let instances: Vec<Arc<Instance>> = instances.into_iter().map(Arc::new).collect();
+        let top = Self::build_top(&instances, if_dark);
+        Self {
+            instances,
+            top,
+            if_dark,
+        }
+    }
+
+    fn build_top(instances: &[Arc<Instance>], if_dark: bool) -> LinearBvh {
+        let objects = instances
+            .iter()
+            .map(|inst| inst.clone() as Arc<dyn Object>)
+            .collect();
+        LinearBvh::new(objects, 0.0, 1.0, if_dark, SplitMethod::Sah)
+    }
+
+    pub fn instances(&self) -> &[Arc<Instance>] {
+        &self.instances
+    }
+
+    /// Gives each listed instance its new matrix (a singular one hides it),
+    /// then rebuilds the top level once for all of them. The bottom-level
+    /// structures are left untouched.
+    pub fn set_matrices<I>(&mut self, moves: I)
+    where
+        I: IntoIterator<Item = (usize, Mat4)>,
+    {
+        for (index, matrix) in moves {
+            Arc::make_mut(&mut self.instances[index]).set_matrix(matrix);
+        }
+        self.top = Self::build_top(&self.instances, self.if_dark);
+    }
+}
+
+impl Object for Tlas {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.top.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.top.bounding_box(t0, t1)
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.top.get_background(t)
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.top.occluded(ray, t_min, t_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_instance() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(&Color::ones()));
+        // a row of small spheres along x, shared by both instances
+        let blas = build_blas(
+            (0..10)
+                .map(|i| {
+                    Arc::new(Sphere::new(
+                        Point3::new(i as f64, 0.0, 0.0),
+                        0.4,
+                        mat.clone(),
+                    )) as Arc<dyn Object>
+                })
+                .collect(),
+        );
+        let mut tlas = Tlas::new(
+            vec![
+                Instance::new(blas.clone(), Mat4::translation(Vec3::new(0.0, 0.0, -5.0))),
+                Instance::new(blas.clone(), Mat4::translation(Vec3::new(0.0, 3.0, -5.0))),
+            ],
+            true,
+        );
+        assert_eq!(Arc::strong_count(&blas), 3);
+
+        let ray = Ray::new(Point3::new(4.0, 3.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = tlas.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 4.6).abs() < 1e-9);
+
+        // pull the second row closer
+        tlas.set_matrices(vec![(1, Mat4::translation(Vec3::new(0.0, 3.0, -2.0)))]);
+        let rec = tlas.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 1.6).abs() < 1e-9);
+        assert!(tlas.occluded(&ray, 0.001, 2.0));
+        assert!(!tlas.occluded(&ray, 0.001, 1.5));
+
+        // hiding it with a zero scale leaves nothing on that row
+        tlas.set_matrices(vec![(1, Mat4::scaling(Vec3::zero()))]);
+        assert!(tlas.instances()[1].is_hidden());
+        assert!(tlas.hit(&ray, 0.001, 100.0).is_none());
+
+        // the old top level is gone, the geometry is still shared
+        assert_eq!(Arc::strong_count(&blas), 3);
+    }
+}
@@ -0,0 +1,253 @@
+pub use crate::object::*;
+use std::f64::consts::PI;
+
+/// Real roots of `a x^2 + b x + c`, ascending. Uses the cancellation-free
+/// form of the quadratic formula.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-14 {
+        if b.abs() < 1e-14 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let mut roots = if q.abs() < 1e-300 {
+        vec![0.0, 0.0]
+    } else {
+        vec![q / a, c / q]
+    };
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+/// Real roots of the monic cubic `x^3 + a x^2 + b x + c`, ascending.
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let q3 = q * q * q;
+
+    let mut roots = if r * r < q3 {
+        // three real roots
+        let theta = (r / q3.sqrt()).max(-1.0).min(1.0).acos();
+        let m = -2.0 * q.sqrt();
+        vec![
+            m * (theta / 3.0).cos() - a / 3.0,
+            m * ((theta + 2.0 * PI) / 3.0).cos() - a / 3.0,
+            m * ((theta - 2.0 * PI) / 3.0).cos() - a / 3.0,
+        ]
+    } else {
+        let big_a = -r.signum() * (r.abs() + (r * r - q3).sqrt()).cbrt();
+        let big_b = if big_a.abs() > 1e-300 { q / big_a } else { 0.0 };
+        vec![big_a + big_b - a / 3.0]
+    };
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+/// Real roots of `c[0] x^4 + c[1] x^3 + c[2] x^2 + c[3] x + c[4]`, ascending.
+///
+/// Ferrari's method through the resolvent cubic, followed by a few Newton
+/// steps on the original polynomial to win back the precision lost in the
+/// reduction. Callers should keep the coefficients well scaled, e.g. by
+/// moving the ray origin close to the surface first.
+pub fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    if c[0].abs() < 1e-14 {
+        let mut roots = solve_cubic_general(c[1], c[2], c[3], c[4]);
+        roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        return roots;
+    }
+    let (a, b, cc, d) = (c[1] / c[0], c[2] / c[0], c[3] / c[0], c[4] / c[0]);
+
+    // depressed quartic y^4 + p y^2 + q y + r with x = y - a / 4
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = cc - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * cc / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut ys = vec![];
+    if q.abs() < 1e-12 {
+        // biquadratic
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                ys.push(z.sqrt());
+                ys.push(-z.sqrt());
+            }
+        }
+    } else {
+        // the largest root of the resolvent is positive whenever q != 0
+        let m = *solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .last()
+            .unwrap();
+        if m > 0.0 {
+            let s = (2.0 * m).sqrt();
+            ys.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+            ys.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+        }
+    }
+
+    let f = |x: f64| (((x + a) * x + b) * x + cc) * x + d;
+    let df = |x: f64| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + cc;
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| {
+            let mut x = y - a / 4.0;
+            for _ in 0..3 {
+                let slope = df(x);
+                if slope.abs() < 1e-14 {
+                    break;
+                }
+                x -= f(x) / slope;
+            }
+            x
+        })
+        .collect();
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+fn solve_cubic_general(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < 1e-14 {
+        solve_quadratic(b, c, d)
+    } else {
+        solve_cubic(b / a, c / a, d / a)
+    }
+}
+
+/// Torus around the y axis through `center`: a tube of `minor_radius`
+/// swept around a circle of `major_radius`.
+pub struct Torus {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+    mp: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3,
+        major_radius: f64,
+        minor_radius: f64,
+        mp: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            mp,
+        }
+    }
+
+    fn aabb(&self) -> AABB {
+        let extent = Vec3::new(
+            self.major_radius + self.minor_radius,
+            self.minor_radius,
+            self.major_radius + self.minor_radius,
+        );
+        AABB::new(self.center - extent, self.center + extent)
+    }
+}
+
+impl Object for Torus {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = self.aabb().hit_range(ray, t_min, t_max)?;
+
+        // restart the ray at the box entry with a unit direction, which keeps
+        // the quartic's coefficients small and its roots accurate
+        let dir_length = ray.dir.length();
+        let d = ray.dir / dir_length;
+        let o = ray.at(t_enter) - self.center;
+
+        let r2 = self.major_radius * self.major_radius;
+        let f = o * d;
+        let e = o.squared_length() - r2 - self.minor_radius * self.minor_radius;
+        let coeffs = [
+            1.0,
+            4.0 * f,
+            4.0 * f * f + 2.0 * e + 4.0 * r2 * d.y * d.y,
+            4.0 * f * e + 8.0 * r2 * o.y * d.y,
+            e * e + 4.0 * r2 * (o.y * o.y - self.minor_radius * self.minor_radius),
+        ];
+
+        let tin = solve_quartic(coeffs)
+            .into_iter()
+            .map(|s| t_enter + s / dir_length)
+            .find(|&tin| tin > t_min && tin < t_max && tin <= t_exit + 1e-9)?;
+
+        let p = ray.at(tin) - self.center;
+        let k = p.squared_length() - r2 - self.minor_radius * self.minor_radius;
+        let outward_normal = (p * k + Vec3::new(0.0, 2.0 * r2 * p.y, 0.0)).unit();
+
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        let u = (p.z.atan2(p.x) + PI) / (2.0 * PI);
+        let v = (p.y.atan2(ring) + PI) / (2.0 * PI);
+
+        let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, self.mp.clone());
+        rec.set_face_normal(ray, &outward_normal);
+        rec.set_uv((u, v));
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(self.aabb())
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(found: Vec<f64>, expected: &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+        for (x, y) in found.iter().zip(expected) {
+            assert!((x - y).abs() < 1e-9, "{:?} != {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn test_solve_quartic() {
+        // (x + 3)(x - 0.5)(x - 1)(x - 2)
+        assert_roots(
+            solve_quartic([1.0, -0.5, -7.0, 9.5, -3.0]),
+            &[-3.0, 0.5, 1.0, 2.0],
+        );
+        // (x^2 + 1)(x - 1)(x - 4): two complex roots
+        assert_roots(solve_quartic([1.0, -5.0, 5.0, -5.0, 4.0]), &[1.0, 4.0]);
+        // biquadratic (x^2 - 1)(x^2 - 9)
+        assert_roots(
+            solve_quartic([1.0, 0.0, -10.0, 0.0, 9.0]),
+            &[-3.0, -1.0, 1.0, 3.0],
+        );
+    }
+
+    #[test]
+    fn test_torus_hit() {
+        let torus = Torus::new(
+            Point3::zero(),
+            2.0,
+            0.5,
+            Arc::new(Lambertian::new(&Color::ones())),
+        );
+        let ray = Ray::new(Point3::new(-10.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), 0.0);
+        let rec = torus.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.p.x + 2.5).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // straight down through the hole
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(torus.hit(&ray, 0.001, 100.0).is_none());
+
+        // from inside the tube
+        let ray = Ray::new(Point3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let rec = torus.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-9);
+        assert!(!rec.front_face);
+    }
+}
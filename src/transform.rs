@@ -0,0 +1,437 @@
+pub use crate::camera::degrees_to_radians;
+pub use crate::object::*;
+use std::ops::Mul;
+
+/// Row-major 4x4 matrix acting on column vectors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Self { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut res = Self::identity();
+        res.m[0][3] = offset.x;
+        res.m[1][3] = offset.y;
+        res.m[2][3] = offset.z;
+        res
+    }
+
+    pub fn scaling(factor: Vec3) -> Self {
+        let mut res = Self::identity();
+        res.m[0][0] = factor.x;
+        res.m[1][1] = factor.y;
+        res.m[2][2] = factor.z;
+        res
+    }
+
+    /// Counter-clockwise rotation around the x (0), y (1) or z (2) axis.
+    pub fn rotation(axis: usize, degrees: f64) -> Self {
+        let (sin_theta, cos_theta) = degrees_to_radians(degrees).sin_cos();
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut res = Self::identity();
+        res.m[a][a] = cos_theta;
+        res.m[a][b] = -sin_theta;
+        res.m[b][a] = sin_theta;
+        res.m[b][b] = cos_theta;
+        res
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut res = [[0.0; 4]; 4];
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = self.m[j][i];
+            }
+        }
+        Self { m: res }
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting, `None` if singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let div = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= div;
+                inv[col][j] /= div;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let factor = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[col][j];
+                        inv[row][j] -= factor * inv[col][j];
+                    }
+                }
+            }
+        }
+
+        Some(Self { m: inv })
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        Point3::new(x, y, z) / w
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// `transform_vector` with the transpose of the matrix, without building
+    /// it. Called on an inverse, this carries normals to the other space.
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        )
+    }
+
+    /// Box enclosing all eight transformed corners of `b`.
+    pub fn transform_box(&self, b: &AABB) -> AABB {
+        let mut small = Point3::ones() * std::f64::INFINITY;
+        let mut big = Point3::ones() * -std::f64::INFINITY;
+
+        for i in 0..8 {
+            let corner = Point3::new(
+                if i & 1 == 0 { b.min_p.x } else { b.max_p.x },
+                if i & 2 == 0 { b.min_p.y } else { b.max_p.y },
+                if i & 4 == 0 { b.min_p.z } else { b.max_p.z },
+            );
+            let p = self.transform_point(corner);
+            small = Point3::new(small.x.min(p.x), small.y.min(p.y), small.z.min(p.z));
+            big = Point3::new(big.x.max(p.x), big.y.max(p.y), big.z.max(p.z));
+        }
+
+        AABB::new(small, big)
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut res = [[0.0; 4]; 4];
+        for (i, row) in res.iter_mut().enumerate() {
+            for (j, val) in row.iter_mut().enumerate() {
+                *val = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Self { m: res }
+    }
+}
+
+pub struct Translate {
+    ptr: Arc<dyn Object>,
+    offset0: Vec3,
+    offset1: Vec3,
+    time0: f64,
+    time1: f64,
+}
+
+impl Translate {
+    pub fn new(ptr: Arc<dyn Object>, offset: Vec3) -> Self {
+        Self::new_moving(ptr, offset, offset, 0.0, 1.0)
+    }
+
+    /// Translation that moves linearly from `offset0` at `time0` to
+    /// `offset1` at `time1`, for motion-blurred instances.
+    pub fn new_moving(
+        ptr: Arc<dyn Object>,
+        offset0: Vec3,
+        offset1: Vec3,
+        time0: f64,
+        time1: f64,
+    ) -> Self {
+        Self {
+            ptr,
+            offset0,
+            offset1,
+            time0,
+            time1,
+        }
+    }
+
+    pub fn offset(&self, time: f64) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.offset0;
+        }
+        self.offset0
+            + (self.offset1 - self.offset0) * ((time - self.time0) / (self.time1 - self.time0))
+    }
+}
+
+impl Object for Translate {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let offset = self.offset(ray.tm);
+        let moved_r = Ray::new(ray.orig - offset, ray.dir, ray.tm);
+        let mut rec = self.ptr.hit(&moved_r, t_min, t_max)?;
+        rec.p += offset;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, t0: f64, t1: f64) -> Option<AABB> {
+        let inner = self.ptr.bounding_box(t0, t1)?;
+        let (offset0, offset1) = (self.offset(t0), self.offset(t1));
+        Some(surrounding_box(
+            AABB::new(inner.min_p + offset0, inner.max_p + offset0),
+            AABB::new(inner.min_p + offset1, inner.max_p + offset1),
+        ))
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.ptr.get_background(t)
+    }
+}
+
+/// Shared implementation of `RotateX`, `RotateY` and `RotateZ`.
+struct AxisRotation {
+    ptr: Arc<dyn Object>,
+    axis: usize,
+    sin_theta: f64,
+    cos_theta: f64,
+    bbox: Option<AABB>,
+}
+
+impl AxisRotation {
+    fn new(ptr: Arc<dyn Object>, axis: usize, degrees: f64) -> Self {
+        let (sin_theta, cos_theta) = degrees_to_radians(degrees).sin_cos();
+        let bbox = ptr
+            .bounding_box(0.0, 1.0)
+            .map(|b| Mat4::rotation(axis, degrees).transform_box(&b));
+        Self {
+            ptr,
+            axis,
+            sin_theta,
+            cos_theta,
+            bbox,
+        }
+    }
+
+    /// Rotates `v` by `sin_theta` (the negated sine gives the inverse).
+    fn rotate(&self, v: Vec3, sin_theta: f64) -> Vec3 {
+        let (a, b) = ((self.axis + 1) % 3, (self.axis + 2) % 3);
+        let mut res = [v.x, v.y, v.z];
+        res[a] = self.cos_theta * v[a] - sin_theta * v[b];
+        res[b] = sin_theta * v[a] + self.cos_theta * v[b];
+        Vec3::new(res[0], res[1], res[2])
+    }
+
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let rotated_r = Ray::new(
+            self.rotate(ray.orig, -self.sin_theta),
+            self.rotate(ray.dir, -self.sin_theta),
+            ray.tm,
+        );
+        let mut rec = self.ptr.hit(&rotated_r, t_min, t_max)?;
+        rec.p = self.rotate(rec.p, self.sin_theta);
+        rec.normal = self.rotate(rec.normal, self.sin_theta);
+        Some(rec)
+    }
+}
+
+pub struct RotateX(AxisRotation);
+pub struct RotateY(AxisRotation);
+pub struct RotateZ(AxisRotation);
+
+impl RotateX {
+    pub fn new(ptr: Arc<dyn Object>, degrees: f64) -> Self {
+        Self(AxisRotation::new(ptr, 0, degrees))
+    }
+}
+
+impl RotateY {
+    pub fn new(ptr: Arc<dyn Object>, degrees: f64) -> Self {
+        Self(AxisRotation::new(ptr, 1, degrees))
+    }
+}
+
+impl RotateZ {
+    pub fn new(ptr: Arc<dyn Object>, degrees: f64) -> Self {
+        Self(AxisRotation::new(ptr, 2, degrees))
+    }
+}
+
+impl Object for RotateX {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.0.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.0.bbox
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.0.ptr.get_background(t)
+    }
+}
+
+impl Object for RotateY {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.0.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.0.bbox
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.0.ptr.get_background(t)
+    }
+}
+
+impl Object for RotateZ {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.0.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.0.bbox
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.0.ptr.get_background(t)
+    }
+}
+
+/// General affine instance transform: `matrix` maps object space to world
+/// space. The ray is taken into object space with the inverse, and normals
+/// come back out with the inverse transpose. A matrix that can't be
+/// inverted, such as a zero scale, hides the object, as with `Instance`.
+pub struct Transform {
+    ptr: Arc<dyn Object>,
+    inverse: Option<Mat4>,
+    bbox: Option<AABB>,
+}
+
+impl Transform {
+    pub fn new(ptr: Arc<dyn Object>, matrix: Mat4) -> Self {
+        let bbox = ptr.bounding_box(0.0, 1.0).map(|b| matrix.transform_box(&b));
+        Self {
+            ptr,
+            inverse: matrix.inverse(),
+            bbox,
+        }
+    }
+}
+
+impl Object for Transform {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let inverse = self.inverse.as_ref()?;
+        let local_r = Ray::new(
+            inverse.transform_point(ray.orig),
+            inverse.transform_vector(ray.dir),
+            ray.tm,
+        );
+        let mut rec = self.ptr.hit(&local_r, t_min, t_max)?;
+        // affine maps keep the ray parameter, so the world ray gives the point
+        rec.p = ray.at(rec.t);
+        rec.normal = inverse.transform_normal(rec.normal).unit();
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        self.bbox
+    }
+
+    fn get_background(&self, t: f64) -> Color {
+        self.ptr.get_background(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let rot = Mat4::rotation(1, 90.0);
+        assert_close(
+            rot.transform_point(Point3::new(1.0, 0.0, 0.0)),
+            Point3::new(0.0, 0.0, -1.0),
+        );
+        let rot = Mat4::rotation(2, 90.0);
+        assert_close(
+            rot.transform_vector(Vec3::new(1.0, 0.0, 0.0)),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotation(0, 30.0)
+            * Mat4::scaling(Vec3::new(2.0, 3.0, 4.0));
+        let p = Point3::new(0.3, -0.7, 5.0);
+        let inv = m.inverse().unwrap();
+        assert_close(inv.transform_point(m.transform_point(p)), p);
+        assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn test_transformed_sphere() {
+        let sphere = Arc::new(Sphere::new(
+            Point3::zero(),
+            1.0,
+            Arc::new(Lambertian::new(&Color::ones())),
+        ));
+        let m = Mat4::translation(Vec3::new(0.0, 0.0, -5.0)) * Mat4::scaling(Vec3::ones() * 2.0);
+        let obj = Transform::new(sphere, m);
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = obj.hit(&ray, 0.001, 100.0).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-9);
+        assert_close(rec.p, Point3::new(0.0, 0.0, -3.0));
+        assert_close(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_singular_matrix() {
+        let sphere = Arc::new(Sphere::new(
+            Point3::zero(),
+            1.0,
+            Arc::new(Lambertian::new(&Color::ones())),
+        ));
+        let flat = Transform::new(sphere, Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)));
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        assert!(flat.hit(&ray, 0.001, 100.0).is_none());
+    }
+}
@@ -0,0 +1,277 @@
+pub use crate::object::*;
+pub use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Triangle {
+    pub vertices: [Point3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: Option<[(f64, f64); 3]>,
+    pub colors: Option<[Color; 3]>,
+    pub mat_ptr: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, m: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [v0, v1, v2],
+            normals: None,
+            uvs: None,
+            colors: None,
+            mat_ptr: m,
+        }
+    }
+
+    /// Triangle with optional per-vertex normals (for smooth shading) and
+    /// texture coordinates. Missing attributes fall back to the face normal
+    /// and the barycentric coordinates respectively.
+    pub fn new_with_attributes(
+        vertices: [Point3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: Option<[(f64, f64); 3]>,
+        m: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            vertices,
+            normals,
+            uvs,
+            colors: None,
+            mat_ptr: m,
+        }
+    }
+}
+
+/// Möller–Trumbore test, returning `t` and the barycentric weights of the
+/// second and third vertex.
+pub fn intersect(
+    vertices: &[Point3; 3],
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let [v0, v1, v2] = *vertices;
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let pvec = Vec3::cross(ray.dir, edge2);
+    let det = edge1 * pvec;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.orig - v0;
+    let b1 = (tvec * pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(tvec, edge1);
+    let b2 = (ray.dir * qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let tin = (edge2 * qvec) * inv_det;
+    if tin < t_min || tin > t_max {
+        return None;
+    }
+    Some((tin, b1, b2))
+}
+
+/// Builds the hit record for a triangle hit, interpolating whichever
+/// per-vertex attributes are present.
+pub fn shade(
+    vertices: &[Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    colors: Option<[Color; 3]>,
+    mat_ptr: &Arc<dyn Material>,
+    ray: &Ray,
+    (tin, b1, b2): (f64, f64, f64),
+) -> HitRecord {
+    let b0 = 1.0 - b1 - b2;
+    let [v0, v1, v2] = *vertices;
+    let outward_normal = Vec3::cross(v1 - v0, v2 - v0).unit();
+    let mut rec = HitRecord::new(ray.at(tin), outward_normal, tin, mat_ptr.clone());
+    rec.set_face_normal(ray, &outward_normal);
+
+    if let Some([n0, n1, n2]) = normals {
+        let shading_normal = n0 * b0 + n1 * b1 + n2 * b2;
+        if shading_normal.squared_length() > 0.0 {
+            let shading_normal = shading_normal.unit();
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+    }
+
+    match uvs {
+        Some([uv0, uv1, uv2]) => rec.set_uv((
+            uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2,
+            uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2,
+        )),
+        None => rec.set_uv((b1, b2)),
+    }
+
+    if let Some([c0, c1, c2]) = colors {
+        rec.color = Some(c0 * b0 + c1 * b1 + c2 * b2);
+    }
+    rec
+}
+
+fn triangle_box(vertices: &[Point3; 3]) -> AABB {
+    let [v0, v1, v2] = *vertices;
+    let small = Point3::new(
+        v0.x.min(v1.x).min(v2.x),
+        v0.y.min(v1.y).min(v2.y),
+        v0.z.min(v1.z).min(v2.z),
+    );
+    let big = Point3::new(
+        v0.x.max(v1.x).max(v2.x),
+        v0.y.max(v1.y).max(v2.y),
+        v0.z.max(v1.z).max(v2.z),
+    );
+    // keep axis-aligned triangles from producing a zero-width box
+    AABB::new(small - 0.0001, big + 0.0001)
+}
+
+impl Object for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let res = intersect(&self.vertices, ray, t_min, t_max)?;
+        Some(shade(
+            &self.vertices,
+            self.normals,
+            self.uvs,
+            self.colors,
+            &self.mat_ptr,
+            ray,
+            res,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(triangle_box(&self.vertices))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+/// Indexed triangle mesh. Vertex attributes are stored once and shared by
+/// all faces, which keeps large scanned meshes affordable compared to
+/// standalone `Triangle`s.
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub colors: Option<Vec<Color>>,
+    pub faces: Vec<[u32; 3]>,
+    pub mat_ptr: Arc<dyn Material>,
+}
+
+impl TriangleMesh {
+    /// One `MeshTriangle` per face, ready to be put into a `BvhNode`.
+    pub fn into_triangles(self) -> Vec<Arc<dyn Object>> {
+        let n_faces = self.faces.len();
+        let mesh = Arc::new(self);
+        (0..n_faces)
+            .map(|face| {
+                Arc::new(MeshTriangle {
+                    mesh: mesh.clone(),
+                    face: face as u32,
+                }) as Arc<dyn Object>
+            })
+            .collect()
+    }
+
+    fn gather<T: Copy>(attr: &Option<Vec<T>>, idx: [u32; 3]) -> Option<[T; 3]> {
+        attr.as_ref()
+            .map(|a| [a[idx[0] as usize], a[idx[1] as usize], a[idx[2] as usize]])
+    }
+}
+
+/// One face of a `TriangleMesh`.
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: u32,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [Point3; 3] {
+        let idx = self.mesh.faces[self.face as usize];
+        let pos = &self.mesh.positions;
+        [
+            pos[idx[0] as usize],
+            pos[idx[1] as usize],
+            pos[idx[2] as usize],
+        ]
+    }
+}
+
+impl Object for MeshTriangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let vertices = self.vertices();
+        let res = intersect(&vertices, ray, t_min, t_max)?;
+        let idx = self.mesh.faces[self.face as usize];
+        Some(shade(
+            &vertices,
+            TriangleMesh::gather(&self.mesh.normals, idx),
+            TriangleMesh::gather(&self.mesh.uvs, idx),
+            TriangleMesh::gather(&self.mesh.colors, idx),
+            &self.mesh.mat_ptr,
+            ray,
+            res,
+        ))
+    }
+
+    fn bounding_box(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        Some(triangle_box(&self.vertices()))
+    }
+
+    fn get_background(&self, _t: f64) -> Color {
+        Color::zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_triangle() -> Triangle {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Arc::new(Lambertian::new(&Color::ones())),
+        )
+    }
+
+    #[test]
+    fn test_hit_barycentric() {
+        let ray = Ray::new(Point3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let rec = unit_triangle().hit(&ray, 0.001, 10.0).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-9);
+        assert!((rec.u - 0.25).abs() < 1e-9);
+        assert!((rec.v - 0.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_miss() {
+        let ray = Ray::new(Point3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(unit_triangle().hit(&ray, 0.001, 10.0).is_none());
+    }
+
+    #[test]
+    fn test_smooth_normal_faces_ray() {
+        let mut tri = unit_triangle();
+        tri.normals = Some([Vec3::new(0.0, 0.0, 1.0); 3]);
+        let ray = Ray::new(Point3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let rec = tri.hit(&ray, 0.001, 10.0).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, Vec3::new(0.0, 0.0, -1.0));
+    }
+}